@@ -0,0 +1,350 @@
+use std::{
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{IoError, Operation};
+
+/// How deeply argfiles may be nested by default
+const DEFAULT_MAX_DEPTH: usize = 16;
+
+/// Expands `@path` arguments into the arguments listed in the file at `path`
+///
+/// This works around the limits operating systems put on the length of command
+/// lines, by passing long lists of arguments in files. The arguments are expanded
+/// before clap sees them
+///
+/// ```rust,no_run
+/// # use clap::Parser;
+/// # use clap_file::{ArgFiles, NamedFile};
+/// #[derive(clap::Parser)]
+/// struct CliArgs {
+///     inputs: Vec<NamedFile>,
+/// }
+///
+/// let args = ArgFiles::new().expand(std::env::args_os())?;
+/// let args = CliArgs::parse_from(args);
+/// # Ok::<_, clap_file::IoError>(())
+/// ```
+///
+/// Argfiles are split into arguments like a shell would split them
+///
+/// * arguments are separated by whitespace, including newlines
+/// * `'single quotes'` keep everything until the next `'`
+/// * `"double quotes"` keep everything until the next `"`, except that `\"` and `\\`
+///   are escaped
+/// * outside of quotes, `\` escapes the next character
+/// * `#` at the start of an argument starts a comment, which lasts until the end of the line
+///
+/// Argfiles may contain `@path` arguments themselves, those paths are relative to
+/// the directory of the argfile containing them. The first argument (the program
+/// name), a lone `@` and all arguments after the first `--` are never expanded,
+/// whether the `--` is on the command line or in an argfile.
+#[derive(Copy, Clone, Debug)]
+pub struct ArgFiles {
+    max_depth: usize,
+}
+
+impl Default for ArgFiles {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ArgFiles {
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    /// How deeply argfiles may be nested inside of other argfiles, 16 by default
+    ///
+    /// This also stops argfiles which include themselves
+    #[inline]
+    pub const fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Expands all `@path` arguments in `args`
+    ///
+    /// ```rust
+    /// # use clap_file::ArgFiles;
+    /// # let dir = std::env::temp_dir().join(format!("clap-file-argfile-{}", std::process::id()));
+    /// # std::fs::create_dir_all(&dir).unwrap();
+    /// let argfile = dir.join("args.txt");
+    /// std::fs::write(&argfile, "# inputs\na.txt 'with space.txt'\n--verbose\n").unwrap();
+    ///
+    /// let argfile = format!("@{}", argfile.display());
+    /// let args = ArgFiles::new()
+    ///     .expand(["prog", &argfile, "b.txt"])
+    ///     .unwrap();
+    /// assert_eq!(args, ["prog", "a.txt", "with space.txt", "--verbose", "b.txt"]);
+    ///
+    /// let args = ArgFiles::new().expand(["prog", "--", &argfile]).unwrap();
+    /// assert_eq!(args, ["prog", "--", &argfile]);
+    /// # std::fs::remove_dir_all(&dir).unwrap();
+    /// ```
+    pub fn expand<I>(&self, args: I) -> Result<Vec<OsString>, IoError>
+    where
+        I: IntoIterator,
+        I::Item: Into<OsString>,
+    {
+        let mut args = args.into_iter().map(Into::into);
+        let mut expanded = Vec::new();
+        expanded.extend(args.next());
+        self.expand_args(args, None, 1, &mut expanded)?;
+        Ok(expanded)
+    }
+
+    /// Appends `args` to `expanded`, expanding `@path` arguments until the first `--`
+    ///
+    /// Nested argfiles are resolved against `dir`. Returns whether `args` contained
+    /// `--`, so the arguments following the argfile aren't expanded either.
+    fn expand_args(
+        &self,
+        mut args: impl Iterator<Item = OsString>,
+        dir: Option<&Path>,
+        depth: usize,
+        expanded: &mut Vec<OsString>,
+    ) -> Result<bool, IoError> {
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                expanded.push(arg);
+                expanded.extend(args);
+                return Ok(true);
+            }
+
+            let Some(path) = argfile_path(&arg) else {
+                expanded.push(arg);
+                continue;
+            };
+
+            let path = match dir {
+                Some(dir) => dir.join(path),
+                None => path,
+            };
+
+            if self.expand_file(path, depth, expanded)? {
+                expanded.extend(args);
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Appends the arguments listed in the argfile at `path` to `expanded`,
+    /// returning whether the argfile contained `--`
+    fn expand_file(
+        &self,
+        path: PathBuf,
+        depth: usize,
+        expanded: &mut Vec<OsString>,
+    ) -> Result<bool, IoError> {
+        if depth > self.max_depth {
+            return Err(IoError::new(
+                Operation::Read,
+                path,
+                io::Error::other(format!(
+                    "argfiles are nested more than {} levels deep",
+                    self.max_depth
+                )),
+            ));
+        }
+
+        let contents = fs::read(&path).map_err(|err| IoError::new(Operation::Open, &path, err))?;
+        let args = split(&contents).map_err(|err| IoError::new(Operation::Read, &path, err))?;
+
+        self.expand_args(
+            args.into_iter(),
+            Some(crate::parent_dir(&path)),
+            depth + 1,
+            expanded,
+        )
+    }
+}
+
+/// The path of an `@path` argument
+fn argfile_path(arg: &OsString) -> Option<PathBuf> {
+    let path = arg
+        .as_encoded_bytes()
+        .strip_prefix(b"@")
+        .filter(|path| !path.is_empty())?;
+
+    // SAFETY: `path` is `arg` without its first character, which is ASCII
+    Some(unsafe { OsString::from_encoded_bytes_unchecked(path.to_vec()) }.into())
+}
+
+/// Splits the contents of an argfile into arguments, like a shell would
+fn split(contents: &[u8]) -> io::Result<Vec<OsString>> {
+    let mut args = Vec::new();
+    let mut current: Option<Vec<u8>> = None;
+    let mut bytes = contents.iter().copied();
+    let mut line = 1;
+
+    let unterminated = |quote: char, line: usize| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("the quote ({quote}) on line {line} is never closed"),
+        )
+    };
+
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'\n' | b' ' | b'\t' | b'\r' => {
+                if byte == b'\n' {
+                    line += 1;
+                }
+                args.extend(
+                    current
+                        .take()
+                        .map(crate::os_string_from_bytes)
+                        .transpose()?,
+                );
+            }
+            b'#' if current.is_none() => {
+                if bytes.by_ref().any(|byte| byte == b'\n') {
+                    line += 1;
+                }
+            }
+            b'\'' => {
+                let start = line;
+                let arg = current.get_or_insert_with(Vec::new);
+                loop {
+                    match bytes.next() {
+                        Some(b'\'') => break,
+                        Some(byte) => {
+                            line += usize::from(byte == b'\n');
+                            arg.push(byte);
+                        }
+                        None => return Err(unterminated('\'', start)),
+                    }
+                }
+            }
+            b'"' => {
+                let start = line;
+                let arg = current.get_or_insert_with(Vec::new);
+                loop {
+                    match bytes.next() {
+                        Some(b'"') => break,
+                        Some(b'\\') => match bytes.next() {
+                            Some(byte @ (b'"' | b'\\')) => arg.push(byte),
+                            Some(byte) => {
+                                line += usize::from(byte == b'\n');
+                                arg.extend([b'\\', byte]);
+                            }
+                            None => return Err(unterminated('"', start)),
+                        },
+                        Some(byte) => {
+                            line += usize::from(byte == b'\n');
+                            arg.push(byte);
+                        }
+                        None => return Err(unterminated('"', start)),
+                    }
+                }
+            }
+            b'\\' => match bytes.next() {
+                // an escaped newline continues the line
+                Some(b'\n') => line += 1,
+                Some(byte) => current.get_or_insert_with(Vec::new).push(byte),
+                None => current.get_or_insert_with(Vec::new).push(b'\\'),
+            },
+            byte => current.get_or_insert_with(Vec::new).push(byte),
+        }
+    }
+
+    args.extend(current.map(crate::os_string_from_bytes).transpose()?);
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split_str(contents: &str) -> io::Result<Vec<String>> {
+        let args = split(contents.as_bytes())?;
+        Ok(args
+            .into_iter()
+            .map(|arg| arg.into_string().unwrap())
+            .collect())
+    }
+
+    #[test]
+    fn whitespace_separates_arguments() {
+        let args = split_str(" a\tb\r\nc \n\n d").unwrap();
+        assert_eq!(args, ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn unterminated_quotes_report_their_line() {
+        let err = split_str("a\n'b\nc").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "the quote (') on line 2 is never closed");
+
+        let err = split_str("a\nb\n\"c\\\"\nd").unwrap_err();
+        assert_eq!(err.to_string(), "the quote (\") on line 3 is never closed");
+    }
+
+    #[test]
+    fn lines_are_counted_inside_of_quotes_and_comments() {
+        let err = split_str("'a\nb' # comment\n\"c\nd\" \\\n 'e").unwrap_err();
+        assert_eq!(err.to_string(), "the quote (') on line 5 is never closed");
+    }
+
+    #[test]
+    fn backslash_escapes_outside_of_quotes() {
+        let args = split_str(r#"a\ b \'c\' \"d\" \\e f\"#).unwrap();
+        assert_eq!(args, ["a b", "'c'", "\"d\"", "\\e", "f\\"]);
+    }
+
+    #[test]
+    fn backslash_continues_lines() {
+        let args = split_str("a\\\nb c").unwrap();
+        assert_eq!(args, ["ab", "c"]);
+    }
+
+    #[test]
+    fn backslash_inside_of_double_quotes() {
+        let args = split_str(r#""a\"b" "c\\d" "e\nf" "g\'h""#).unwrap();
+        assert_eq!(args, ["a\"b", "c\\d", "e\\nf", "g\\'h"]);
+    }
+
+    #[test]
+    fn backslash_inside_of_single_quotes() {
+        let args = split_str(r"'a\b' 'c\\'").unwrap();
+        assert_eq!(args, ["a\\b", "c\\\\"]);
+    }
+
+    #[test]
+    fn comments_only_start_arguments() {
+        let args = split_str("# comment\na#b # comment 'not a quote\n'#c' \\#d").unwrap();
+        assert_eq!(args, ["a#b", "#c", "#d"]);
+    }
+
+    #[test]
+    fn empty_quotes_are_empty_arguments() {
+        let args = split_str("'' \"\" a''b ''").unwrap();
+        assert_eq!(args, ["", "", "ab", ""]);
+    }
+
+    #[test]
+    fn double_dash_in_an_argfile_stops_the_expansion() {
+        let dir =
+            std::env::temp_dir().join(format!("clap-file-argfile-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("outer.txt"), "a @inner.txt b @inner.txt").unwrap();
+        fs::write(dir.join("inner.txt"), "c -- @d").unwrap();
+
+        let outer = format!("@{}", dir.join("outer.txt").display());
+        let args = ArgFiles::new().expand(["prog", &outer, "@e"]);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let expected = ["prog", "a", "c", "--", "@d", "b", "@inner.txt", "@e"];
+        assert_eq!(args.unwrap(), expected);
+    }
+}
@@ -0,0 +1,449 @@
+use std::{
+    ffi::OsString,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crate::{compress::SharedEncoder, Compression, IoError, Operation};
+
+/// How many temporary file names are tried before giving up
+const MAX_TEMP_ATTEMPTS: usize = 100;
+
+/// Used to make temporary file names unique within this process
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// This represents an output file which is written atomically
+///
+/// All writes go to a temporary file next to the target, which is only
+/// renamed over the target once [`commit`](AtomicOutputFile::commit) is
+/// called. If the output is dropped without being committed, the temporary
+/// file is removed and the target is left untouched.
+///
+/// This can be used with clap's derive API like so
+///
+/// ```rust
+/// # use clap::Parser;
+/// # use clap_file::{AtomicOutputFile, AtomicOutputFileParser};
+/// #[derive(clap::Parser)]
+/// struct CliArgs {
+///     #[arg(short, long, value_parser = AtomicOutputFileParser::new().sync(true))]
+///     output: AtomicOutputFile,
+/// }
+///
+/// let target = std::env::temp_dir().join("clap-file-atomic-example.txt");
+/// # let _ = std::fs::remove_file(&target);
+/// let args = CliArgs::try_parse_from(["prog".as_ref(), "-o".as_ref(), target.as_os_str()]).unwrap();
+/// args.output.write_all(b"hello").unwrap();
+/// assert!(!target.exists());
+///
+/// args.output.commit().unwrap();
+/// assert_eq!(std::fs::read(&target).unwrap(), b"hello");
+/// # std::fs::remove_file(&target).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct AtomicOutputFile {
+    inner: Arc<AtomicInner>,
+}
+
+struct AtomicInner {
+    file: Arc<fs::File>,
+    encoder: Option<SharedEncoder>,
+    path: PathBuf,
+    temp_path: PathBuf,
+    sync: bool,
+    committed: AtomicBool,
+}
+
+/// A clap parser for parsing [`AtomicOutputFiles`](AtomicOutputFile)
+#[derive(Copy, Clone, Debug, Default)]
+pub struct AtomicOutputFileParser {
+    sync: bool,
+    compression: Compression,
+}
+
+impl clap::builder::ValueParserFactory for AtomicOutputFile {
+    type Parser = AtomicOutputFileParser;
+
+    #[inline]
+    fn value_parser() -> Self::Parser {
+        AtomicOutputFileParser::new()
+    }
+}
+
+impl AtomicOutputFileParser {
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            sync: false,
+            compression: Compression::Auto,
+        }
+    }
+
+    /// Fsync the file and its directory when committing
+    #[inline]
+    pub const fn sync(mut self, sync: bool) -> Self {
+        self.sync = sync;
+        self
+    }
+
+    /// How files are compressed, by default the codec is picked from the extension of the target
+    #[inline]
+    pub const fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+}
+
+impl AtomicOutputFile {
+    /// Renames the temporary file over the target path
+    ///
+    /// If the output is compressed, the compressed stream is completed first.
+    /// If fsync was requested on the parser, the file is synced before the
+    /// rename and its directory after it.
+    pub fn commit(self) -> Result<(), IoError> {
+        let inner = &*self.inner;
+
+        if let Some(encoder) = &inner.encoder {
+            encoder
+                .finish()
+                .map_err(|err| self.error(Operation::Write, err))?;
+        }
+
+        if inner.sync {
+            inner
+                .file
+                .sync_all()
+                .map_err(|err| self.error(Operation::Sync, err))?;
+        }
+
+        fs::rename(&inner.temp_path, &inner.path)
+            .map_err(|err| self.error(Operation::Rename, err))?;
+        inner.committed.store(true, Ordering::Release);
+
+        if inner.sync {
+            sync_dir(crate::parent_dir(&inner.path))
+                .map_err(|err| self.error(Operation::Sync, err))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_all(&self, bytes: &[u8]) -> Result<(), IoError> {
+        Write::write_all(&mut &*self, bytes).map_err(|err| self.error(Operation::Write, err))
+    }
+
+    pub fn file(&self) -> &fs::File {
+        &self.inner.file
+    }
+
+    /// The path the file will be renamed to on commit
+    pub fn path(&self) -> &Path {
+        &self.inner.path
+    }
+
+    /// The path of the temporary file which is being written to
+    pub fn temp_path(&self) -> &Path {
+        &self.inner.temp_path
+    }
+
+    fn error(&self, operation: Operation, err: io::Error) -> IoError {
+        IoError::new(operation, &self.inner.path, err)
+    }
+}
+
+impl Write for &AtomicOutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &self.inner.encoder {
+            Some(encoder) => encoder.write(buf),
+            None => self.file().write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(encoder) = &self.inner.encoder {
+            encoder.flush()?;
+        }
+
+        self.file().flush()
+    }
+}
+
+impl Write for AtomicOutputFile {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Write::write(&mut &*self, buf)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Write::flush(&mut &*self)
+    }
+}
+
+impl Drop for AtomicInner {
+    fn drop(&mut self) {
+        if !self.committed.load(Ordering::Acquire) {
+            let _ = fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    fs::File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    // directories can't be opened and synced portably
+    Ok(())
+}
+
+/// Creates a new temporary file next to `path`, with the same permissions as `path` if it exists
+fn create_temp(path: &Path) -> io::Result<(fs::File, PathBuf)> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "the path has no file name"))?;
+    let dir = crate::parent_dir(path);
+    let permissions = fs::metadata(path)
+        .ok()
+        .map(|metadata| metadata.permissions());
+
+    for _ in 0..MAX_TEMP_ATTEMPTS {
+        let mut temp_name = OsString::from(".");
+        temp_name.push(file_name);
+        temp_name.push(format!(
+            ".{}.{}.tmp",
+            std::process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let temp_path = dir.join(temp_name);
+
+        let file = match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+        {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        };
+
+        if let Some(permissions) = permissions {
+            if let Err(err) = file.set_permissions(permissions) {
+                let _ = fs::remove_file(&temp_path);
+                return Err(err);
+            }
+        }
+
+        return Ok((file, temp_path));
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        "could not find an unused temporary file name",
+    ))
+}
+
+impl clap::builder::TypedValueParser for AtomicOutputFileParser {
+    type Value = AtomicOutputFile;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        self.parse(cmd, arg, value.into())
+    }
+
+    fn parse(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: std::ffi::OsString,
+    ) -> Result<Self::Value, clap::Error> {
+        let path = Path::new(&value);
+        let (file, temp_path) =
+            create_temp(path).map_err(|err| crate::open_error(cmd, arg, path, err))?;
+        let file = Arc::new(file);
+
+        let encoder = self
+            .compression
+            .codec(path)
+            .map(|codec| SharedEncoder::new(codec, Box::new(file.clone())))
+            .transpose()
+            .map_err(|err| {
+                let _ = fs::remove_file(&temp_path);
+                crate::open_error(cmd, arg, path, err)
+            })?;
+
+        Ok(AtomicOutputFile {
+            inner: Arc::new(AtomicInner {
+                file,
+                encoder,
+                path: value.into(),
+                temp_path,
+                sync: self.sync,
+                committed: AtomicBool::new(false),
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::builder::TypedValueParser;
+
+    use super::*;
+
+    fn parse(parser: AtomicOutputFileParser, path: &Path) -> AtomicOutputFile {
+        parser
+            .parse_ref(&clap::Command::new("prog"), None, path.as_os_str())
+            .unwrap()
+    }
+
+    /// The names of the entries in `dir`
+    fn entries(dir: &Path) -> Vec<OsString> {
+        let mut entries: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    #[test]
+    fn dropping_without_commit_keeps_the_target() {
+        let dir = crate::test_dir("atomic-drop");
+        let path = dir.join("out.txt");
+        fs::write(&path, "old").unwrap();
+
+        let output = parse(AtomicOutputFileParser::new(), &path);
+        let temp_path = output.temp_path().to_owned();
+        output.write_all(b"new").unwrap();
+        assert_eq!(fs::read(&temp_path).unwrap(), b"new");
+
+        // the temporary file is only removed once the last clone is dropped
+        let clone = output.clone();
+        drop(output);
+        assert!(temp_path.exists());
+
+        drop(clone);
+        assert!(!temp_path.exists());
+        assert_eq!(fs::read(&path).unwrap(), b"old");
+        assert_eq!(entries(&dir), ["out.txt"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn dropping_without_commit_creates_nothing() {
+        let dir = crate::test_dir("atomic-drop-new");
+        let path = dir.join("out.txt");
+
+        let output = parse(AtomicOutputFileParser::new(), &path);
+        output.write_all(b"new").unwrap();
+        drop(output);
+
+        assert!(entries(&dir).is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn commit_replaces_the_target() {
+        let dir = crate::test_dir("atomic-commit");
+        let path = dir.join("out.txt");
+        fs::write(&path, "old").unwrap();
+
+        let output = parse(AtomicOutputFileParser::new().sync(true), &path);
+        let clone = output.clone();
+        output.write_all(b"new").unwrap();
+        output.commit().unwrap();
+
+        // dropping a clone after the commit doesn't remove the committed file
+        drop(clone);
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert_eq!(entries(&dir), ["out.txt"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_parses_leave_no_temporary_files() {
+        let dir = crate::test_dir("atomic-failed");
+        let path = dir.join("missing").join("out.txt");
+
+        let result = AtomicOutputFileParser::new().parse_ref(
+            &clap::Command::new("prog"),
+            None,
+            path.as_os_str(),
+        );
+        assert!(result.is_err());
+        assert!(entries(&dir).is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn the_permissions_of_the_target_are_kept() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = crate::test_dir("atomic-permissions");
+        let path = dir.join("script.sh");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o750)).unwrap();
+
+        let output = parse(AtomicOutputFileParser::new(), &path);
+        let mode = fs::metadata(output.temp_path())
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o750);
+
+        output.write_all(b"new").unwrap();
+        output.commit().unwrap();
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o750);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn compressed_outputs_are_finished_on_commit() {
+        let dir = crate::test_dir("atomic-gzip");
+        let path = dir.join("out.txt.gz");
+
+        let output = parse(AtomicOutputFileParser::new(), &path);
+        output.write_all(b"hello\n").unwrap();
+        output.commit().unwrap();
+
+        let file = crate::NamedFileParser::new().open(&path).unwrap();
+        assert_eq!(file.codec(), Some(crate::Codec::Gzip));
+        assert_eq!(file.read().unwrap(), b"hello\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn compressed_outputs_are_removed_without_commit() {
+        let dir = crate::test_dir("atomic-gzip-drop");
+        let path = dir.join("out.txt.gz");
+
+        let output = parse(AtomicOutputFileParser::new(), &path);
+        output.write_all(b"hello\n").unwrap();
+        drop(output);
+
+        assert!(entries(&dir).is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
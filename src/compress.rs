@@ -0,0 +1,462 @@
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::Path,
+    sync::{Mutex, PoisonError},
+};
+
+/// The compression formats which files can be transparently decompressed from
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Codec {
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "zstd")]
+    Zstd,
+    #[cfg(feature = "xz")]
+    Xz,
+    #[cfg(feature = "bzip2")]
+    Bzip2,
+}
+
+/// How a [`NamedFileParser`](crate::NamedFileParser) decides whether to decompress a file
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub enum Decompression {
+    /// Pick the codec from the file's extension, or from its first bytes (the default)
+    #[default]
+    Auto,
+    /// Never decompress files
+    Disabled,
+    /// Always decompress files with the given codec
+    Force(Codec),
+}
+
+/// How an output parser decides whether to compress a file
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub enum Compression {
+    /// Pick the codec from the file's extension (the default)
+    #[default]
+    Auto,
+    /// Never compress files
+    Disabled,
+    /// Always compress files with the given codec
+    Force(Codec),
+}
+
+/// The longest magic number of any codec
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "xz", feature = "bzip2"))]
+const MAX_MAGIC_LEN: usize = 10;
+
+impl Codec {
+    /// Picks the codec from the extension of `path`
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+        match extension.as_str() {
+            #[cfg(feature = "gzip")]
+            "gz" | "gzip" => Some(Self::Gzip),
+            #[cfg(feature = "zstd")]
+            "zst" | "zstd" => Some(Self::Zstd),
+            #[cfg(feature = "xz")]
+            "xz" => Some(Self::Xz),
+            #[cfg(feature = "bzip2")]
+            "bz2" | "bzip2" => Some(Self::Bzip2),
+            _ => None,
+        }
+    }
+
+    /// Picks the codec from the magic number at the start of a file
+    pub fn from_magic(bytes: &[u8]) -> Option<Self> {
+        #[cfg(feature = "gzip")]
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            return Some(Self::Gzip);
+        }
+
+        #[cfg(feature = "zstd")]
+        if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            return Some(Self::Zstd);
+        }
+
+        #[cfg(feature = "xz")]
+        if bytes.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            return Some(Self::Xz);
+        }
+
+        // `BZh` alone is too likely to start a text file, so the block size
+        // and the magic of the first block (or of the end of an empty stream)
+        // have to match as well
+        #[cfg(feature = "bzip2")]
+        if let [b'B', b'Z', b'h', b'1'..=b'9', block @ ..] = bytes {
+            const BLOCK_MAGIC: [u8; 6] = [0x31, 0x41, 0x59, 0x26, 0x53, 0x59];
+            const END_MAGIC: [u8; 6] = [0x17, 0x72, 0x45, 0x38, 0x50, 0x90];
+
+            if block.starts_with(&BLOCK_MAGIC) || block.starts_with(&END_MAGIC) {
+                return Some(Self::Bzip2);
+            }
+        }
+
+        let _ = bytes;
+        None
+    }
+
+    /// Wraps `reader` in a decoder for this codec
+    pub(crate) fn decoder<'a>(self, reader: Box<dyn Read + 'a>) -> io::Result<Box<dyn Read + 'a>> {
+        let _ = &reader;
+
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip => Ok(Box::new(flate2::read::MultiGzDecoder::new(reader))),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => Ok(Box::new(zstd::stream::read::Decoder::new(reader)?)),
+            #[cfg(feature = "xz")]
+            Self::Xz => Ok(Box::new(xz2::read::XzDecoder::new_multi_decoder(reader))),
+            #[cfg(feature = "bzip2")]
+            Self::Bzip2 => Ok(Box::new(bzip2::read::MultiBzDecoder::new(reader))),
+        }
+    }
+}
+
+impl Compression {
+    /// The codec to compress the file at `path` with
+    pub(crate) fn codec(self, path: &Path) -> Option<Codec> {
+        match self {
+            Self::Auto => Codec::from_path(path),
+            Self::Disabled => None,
+            Self::Force(codec) => Some(codec),
+        }
+    }
+}
+
+impl Codec {
+    /// Wraps `writer` in an encoder for this codec
+    fn encoder(self, writer: Box<dyn Write + Send>) -> io::Result<Box<dyn Encoder>> {
+        let _ = &writer;
+
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip => Ok(Box::new(flate2::write::GzEncoder::new(
+                writer,
+                flate2::Compression::default(),
+            ))),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => Ok(Box::new(zstd::stream::write::Encoder::new(writer, 0)?)),
+            #[cfg(feature = "xz")]
+            Self::Xz => Ok(Box::new(xz2::write::XzEncoder::new(writer, 6))),
+            #[cfg(feature = "bzip2")]
+            Self::Bzip2 => Ok(Box::new(bzip2::write::BzEncoder::new(
+                writer,
+                bzip2::Compression::default(),
+            ))),
+        }
+    }
+}
+
+/// An encoder which has to be finished to write its trailing data
+trait Encoder: Write + Send {
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+#[cfg(feature = "gzip")]
+impl<W: Write + Send> Encoder for flate2::write::GzEncoder<W> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        (*self).finish()?.flush()
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl<W: Write + Send> Encoder for zstd::stream::write::Encoder<'static, W> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        (*self).finish()?.flush()
+    }
+}
+
+#[cfg(feature = "xz")]
+impl<W: Write + Send> Encoder for xz2::write::XzEncoder<W> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        (*self).finish()?.flush()
+    }
+}
+
+#[cfg(feature = "bzip2")]
+impl<W: Write + Send> Encoder for bzip2::write::BzEncoder<W> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        (*self).finish()?.flush()
+    }
+}
+
+/// An encoder which is shared between all clones of an output
+///
+/// The encoder is finished when [`finish`](SharedEncoder::finish) is called,
+/// or when it is dropped
+pub(crate) struct SharedEncoder {
+    encoder: Mutex<Option<Box<dyn Encoder>>>,
+}
+
+impl SharedEncoder {
+    pub(crate) fn new(codec: Codec, writer: Box<dyn Write + Send>) -> io::Result<Self> {
+        Ok(Self {
+            encoder: Mutex::new(Some(codec.encoder(writer)?)),
+        })
+    }
+
+    fn with_encoder<T>(&self, f: impl FnOnce(&mut dyn Encoder) -> io::Result<T>) -> io::Result<T> {
+        let mut encoder = self.encoder.lock().unwrap_or_else(PoisonError::into_inner);
+        match encoder.as_deref_mut() {
+            Some(encoder) => f(encoder),
+            None => Err(io::Error::other(
+                "the compressed output has already been finished",
+            )),
+        }
+    }
+
+    pub(crate) fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        self.with_encoder(|encoder| encoder.write(buf))
+    }
+
+    pub(crate) fn flush(&self) -> io::Result<()> {
+        let mut encoder = self.encoder.lock().unwrap_or_else(PoisonError::into_inner);
+        match encoder.as_deref_mut() {
+            Some(encoder) => encoder.flush(),
+            // everything was already written when the encoder was finished
+            None => Ok(()),
+        }
+    }
+
+    /// Writes the trailing data of the encoder, finishing an already finished encoder does nothing
+    pub(crate) fn finish(&self) -> io::Result<()> {
+        let encoder = self
+            .encoder
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+
+        match encoder {
+            Some(encoder) => encoder.finish(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for SharedEncoder {
+    fn drop(&mut self) {
+        // errors can't be reported here, `finish` should be called to observe them
+        let _ = self.finish();
+    }
+}
+
+impl Decompression {
+    /// The codec to decompress the file at `path` with
+    pub(crate) fn codec(self, path: &Path, file: &fs::File) -> Option<Codec> {
+        match self {
+            Self::Auto => Codec::from_path(path).or_else(|| sniff(file)),
+            Self::Disabled => None,
+            Self::Force(codec) => Some(codec),
+        }
+    }
+}
+
+/// Picks the codec from the first bytes of `file`, without moving its cursor
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "xz", feature = "bzip2"))]
+fn sniff(file: &fs::File) -> Option<Codec> {
+    // only files which can be seeked can be peeked at
+    if !file.metadata().is_ok_and(|metadata| metadata.is_file()) {
+        return None;
+    }
+
+    let mut magic = [0; MAX_MAGIC_LEN];
+    let mut len = 0;
+
+    while len < magic.len() {
+        match crate::read_at(file, &mut magic[len..], len as u64) {
+            Ok(0) => break,
+            Ok(read) => len += read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(_) => return None,
+        }
+    }
+
+    Codec::from_magic(&magic[..len])
+}
+
+#[cfg(not(any(feature = "gzip", feature = "zstd", feature = "xz", feature = "bzip2")))]
+fn sniff(_file: &fs::File) -> Option<Codec> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::NamedFileParser;
+
+    /// The enabled codecs, and an extension which picks them
+    const CODECS: &[(Codec, &str)] = &[
+        #[cfg(feature = "gzip")]
+        (Codec::Gzip, "gz"),
+        #[cfg(feature = "zstd")]
+        (Codec::Zstd, "zst"),
+        #[cfg(feature = "xz")]
+        (Codec::Xz, "xz"),
+        #[cfg(feature = "bzip2")]
+        (Codec::Bzip2, "bz2"),
+    ];
+
+    const TEXT: &[u8] = b"hello\nworld\n";
+
+    /// Writes `data` compressed with `codec` to the file at `path`
+    fn write_compressed(codec: Codec, path: &Path, data: &[u8]) {
+        let file = fs::File::create(path).unwrap();
+        let mut encoder = codec.encoder(Box::new(file)).unwrap();
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap();
+    }
+
+    fn read(parser: NamedFileParser, path: &Path) -> Vec<u8> {
+        parser.open(path).unwrap().read().unwrap()
+    }
+
+    #[test]
+    fn codec_from_path() {
+        for &(codec, extension) in CODECS {
+            let path = PathBuf::from(format!("data.txt.{extension}"));
+            assert_eq!(Codec::from_path(&path), Some(codec));
+
+            let path = PathBuf::from(format!("DATA.{}", extension.to_uppercase()));
+            assert_eq!(Codec::from_path(&path), Some(codec));
+        }
+
+        assert_eq!(Codec::from_path(Path::new("data.txt")), None);
+        assert_eq!(Codec::from_path(Path::new("gz")), None);
+        assert_eq!(Codec::from_path(Path::new("data.gz.txt")), None);
+    }
+
+    #[test]
+    fn codec_from_magic() {
+        #[cfg(feature = "gzip")]
+        assert_eq!(Codec::from_magic(&[0x1f, 0x8b, 0x08]), Some(Codec::Gzip));
+        #[cfg(feature = "zstd")]
+        assert_eq!(
+            Codec::from_magic(&[0x28, 0xb5, 0x2f, 0xfd]),
+            Some(Codec::Zstd)
+        );
+        #[cfg(feature = "xz")]
+        assert_eq!(Codec::from_magic(b"\xfd7zXZ\0"), Some(Codec::Xz));
+        #[cfg(feature = "bzip2")]
+        {
+            assert_eq!(Codec::from_magic(b"BZh91AY&SY"), Some(Codec::Bzip2));
+            assert_eq!(
+                Codec::from_magic(b"BZh1\x17\x72\x45\x38\x50\x90"),
+                Some(Codec::Bzip2)
+            );
+        }
+
+        assert_eq!(Codec::from_magic(b""), None);
+        assert_eq!(Codec::from_magic(&[0x1f]), None);
+        assert_eq!(Codec::from_magic(b"\xfd7zX"), None);
+        assert_eq!(Codec::from_magic(b"BZh"), None);
+        assert_eq!(Codec::from_magic(b"BZh9 is a text file"), None);
+        assert_eq!(Codec::from_magic(b"BZh01AY&SY"), None);
+        assert_eq!(Codec::from_magic(TEXT), None);
+    }
+
+    #[test]
+    fn the_magic_of_every_codec_fits() {
+        let dir = crate::test_dir("magic");
+
+        for &(codec, _) in CODECS {
+            let path = dir.join("empty");
+            write_compressed(codec, &path, b"");
+
+            let file = fs::File::open(&path).unwrap();
+            assert_eq!(sniff(&file), Some(codec));
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn decompress_by_extension() {
+        let dir = crate::test_dir("decompress-extension");
+
+        for &(codec, extension) in CODECS {
+            let path = dir.join(format!("data.txt.{extension}"));
+            write_compressed(codec, &path, TEXT);
+
+            let file = NamedFileParser::new().open(&path).unwrap();
+            assert_eq!(file.codec(), Some(codec));
+            assert_eq!(file.read().unwrap(), TEXT);
+
+            let mut contents = Vec::new();
+            file.reader().unwrap().read_to_end(&mut contents).unwrap();
+            assert_eq!(contents, TEXT);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn decompress_by_magic() {
+        let dir = crate::test_dir("decompress-magic");
+
+        for &(codec, _) in CODECS {
+            let path = dir.join("data");
+            write_compressed(codec, &path, TEXT);
+
+            let file = NamedFileParser::new().open(&path).unwrap();
+            assert_eq!(file.codec(), Some(codec));
+            assert_eq!(file.read().unwrap(), TEXT);
+        }
+
+        let path = dir.join("plain.txt");
+        fs::write(&path, TEXT).unwrap();
+        assert_eq!(NamedFileParser::new().open(&path).unwrap().codec(), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn disabled_decompression() {
+        let dir = crate::test_dir("decompress-disabled");
+
+        for &(codec, extension) in CODECS {
+            let path = dir.join(format!("data.txt.{extension}"));
+            write_compressed(codec, &path, TEXT);
+
+            let parser = NamedFileParser::new().decompression(Decompression::Disabled);
+            assert_eq!(read(parser, &path), fs::read(&path).unwrap());
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn forced_decompression() {
+        let dir = crate::test_dir("decompress-forced");
+
+        for &(codec, _) in CODECS {
+            // neither the extension nor the magic of a different codec matter
+            let path = dir.join("data.txt");
+            write_compressed(codec, &path, TEXT);
+
+            let parser = NamedFileParser::new().decompression(Decompression::Force(codec));
+            assert_eq!(read(parser, &path), TEXT);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn forced_decompression_of_invalid_data_fails() {
+        let dir = crate::test_dir("decompress-invalid");
+        let path = dir.join("data.txt");
+        fs::write(&path, TEXT).unwrap();
+
+        for &(codec, _) in CODECS {
+            let parser = NamedFileParser::new().decompression(Decompression::Force(codec));
+            assert!(parser.open(&path).unwrap().read().is_err());
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,419 @@
+use std::{
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::EscapedPath,
+    parser::{check_kind, has_extension, normalize_extension},
+    Decompression, FileKind, IoError, NamedFile, Operation,
+};
+
+/// This represents a named directory
+///
+/// The directory must exist and be readable, otherwise parsing fails.
+/// Its files are visited with [`walk`](NamedDir::walk).
+///
+/// ```rust
+/// # use clap::Parser;
+/// # use clap_file::NamedDir;
+/// #[derive(clap::Parser)]
+/// struct CliArgs {
+///     inputs: NamedDir,
+/// }
+///
+/// let args = CliArgs::try_parse_from(["prog", "src"]).unwrap();
+/// let files = args
+///     .inputs
+///     .walk()
+///     .extensions(["rs"])
+///     .into_iter()
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+/// assert!(files.iter().any(|file| file.path().ends_with("lib.rs")));
+///
+/// assert!(CliArgs::try_parse_from(["prog", "Cargo.toml"]).is_err());
+/// ```
+#[derive(Clone, Debug)]
+pub struct NamedDir {
+    path: PathBuf,
+}
+
+/// A clap parser for parsing [`NamedDirs`](NamedDir)
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NamedDirParser {
+    _private: (),
+}
+
+/// How a [`Walk`] treats symbolic links
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Symlinks {
+    /// Ignore all symbolic links
+    Skip,
+    /// Visit links to files, but don't descend into links to directories
+    #[default]
+    Files,
+    /// Visit links to files and descend into links to directories,
+    /// links which lead back to one of their own ancestors are reported as errors
+    Follow,
+}
+
+/// A recursive walk over the files in a [`NamedDir`], see [`NamedDir::walk`]
+///
+/// Entries are visited in order of their file names, so walks are deterministic.
+/// Hidden files (whose names start with `.`) are skipped by default.
+#[derive(Clone, Debug)]
+pub struct Walk {
+    root: PathBuf,
+    extensions: Vec<String>,
+    max_depth: Option<usize>,
+    symlinks: Symlinks,
+    hidden: bool,
+    decompression: Decompression,
+    #[cfg(feature = "ignore")]
+    ignore_files: bool,
+}
+
+/// The iterator over the files of a [`Walk`]
+pub struct WalkIter {
+    walk: Walk,
+    stack: Vec<DirFrame>,
+    started: bool,
+}
+
+/// A directory which is being walked
+struct DirFrame {
+    entries: std::vec::IntoIter<PathBuf>,
+    depth: usize,
+    /// the canonical path of the directory, used to detect symlink loops
+    canonical: Option<PathBuf>,
+    #[cfg(feature = "ignore")]
+    ignore: Option<ignore::gitignore::Gitignore>,
+}
+
+impl clap::builder::ValueParserFactory for NamedDir {
+    type Parser = NamedDirParser;
+
+    #[inline]
+    fn value_parser() -> Self::Parser {
+        NamedDirParser::new()
+    }
+}
+
+impl NamedDirParser {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl NamedDir {
+    /// The path of the directory, exactly as it was given
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// A recursive walk over the regular files in the directory
+    pub fn walk(&self) -> Walk {
+        Walk {
+            root: self.path.clone(),
+            extensions: Vec::new(),
+            max_depth: None,
+            symlinks: Symlinks::default(),
+            hidden: false,
+            decompression: Decompression::Auto,
+            #[cfg(feature = "ignore")]
+            ignore_files: false,
+        }
+    }
+}
+
+impl Walk {
+    /// Only visit files with one of the given extensions
+    ///
+    /// Extensions are compared case-insensitively and may contain dots, like `tar.gz`
+    pub fn extensions<I>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.extensions
+            .extend(extensions.into_iter().map(normalize_extension));
+        self
+    }
+
+    /// Only visit files up to `depth` directories deep, files directly
+    /// inside of the directory have a depth of 1
+    ///
+    /// A depth of 0 visits no files at all.
+    ///
+    /// ```rust
+    /// # use clap::Parser;
+    /// # use clap_file::NamedDir;
+    /// #[derive(clap::Parser)]
+    /// struct CliArgs {
+    ///     inputs: NamedDir,
+    /// }
+    ///
+    /// let args = CliArgs::try_parse_from(["prog", "src"]).unwrap();
+    /// assert_eq!(args.inputs.walk().max_depth(0).into_iter().count(), 0);
+    ///
+    /// let files = args.inputs.walk().max_depth(1).into_iter();
+    /// assert!(files.map(Result::unwrap).all(|file| file.path().parent() == Some(args.inputs.path())));
+    /// ```
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    pub fn symlinks(mut self, symlinks: Symlinks) -> Self {
+        self.symlinks = symlinks;
+        self
+    }
+
+    /// Visit hidden files and directories, whose names start with `.`
+    pub fn hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// How files are decompressed, by default the codec is picked from the
+    /// extension or the first bytes of the file
+    pub fn decompression(mut self, decompression: Decompression) -> Self {
+        self.decompression = decompression;
+        self
+    }
+
+    /// Skip the files excluded by `.gitignore` and `.ignore` files, using
+    /// gitignore's syntax. Rules in `.ignore` files take precedence.
+    ///
+    /// Only the ignore files inside of the walked directory are read, and
+    /// `.gitignore` files are read whether or not they are in a git repository.
+    #[cfg(feature = "ignore")]
+    pub fn ignore_files(mut self, ignore_files: bool) -> Self {
+        self.ignore_files = ignore_files;
+        self
+    }
+}
+
+impl IntoIterator for Walk {
+    type Item = Result<NamedFile, IoError>;
+    type IntoIter = WalkIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        WalkIter {
+            walk: self,
+            stack: Vec::new(),
+            started: false,
+        }
+    }
+}
+
+impl WalkIter {
+    /// Visits the entry at `path`, returning it if it is a file
+    fn visit(&mut self, path: PathBuf, depth: usize) -> Result<Option<NamedFile>, IoError> {
+        let hidden = path
+            .file_name()
+            .is_some_and(|name| name.as_encoded_bytes().starts_with(b"."));
+        if hidden && !self.walk.hidden {
+            return Ok(None);
+        }
+
+        let mut metadata = fs::symlink_metadata(&path)
+            .map_err(|err| IoError::new(Operation::Metadata, &path, err))?;
+        let is_symlink = metadata.is_symlink();
+
+        if is_symlink {
+            if self.walk.symlinks == Symlinks::Skip {
+                return Ok(None);
+            }
+
+            metadata =
+                fs::metadata(&path).map_err(|err| IoError::new(Operation::Metadata, &path, err))?;
+        }
+
+        if self.is_ignored(&path, metadata.is_dir()) {
+            return Ok(None);
+        }
+
+        if metadata.is_dir() {
+            let followed = !is_symlink || self.walk.symlinks == Symlinks::Follow;
+            let too_deep = self.walk.max_depth.is_some_and(|max| depth >= max);
+
+            if followed && !too_deep {
+                self.enter(path, depth)?;
+            }
+
+            return Ok(None);
+        }
+
+        let too_deep = self.walk.max_depth.is_some_and(|max| depth > max);
+
+        if too_deep
+            || !metadata.is_file()
+            || !(self.walk.extensions.is_empty() || has_extension(&path, &self.walk.extensions))
+        {
+            return Ok(None);
+        }
+
+        let file =
+            fs::File::open(&path).map_err(|err| IoError::new(Operation::Open, &path, err))?;
+        let codec = self.walk.decompression.codec(&path, &file);
+        Ok(Some(NamedFile::new(file, path).with_codec(codec)))
+    }
+
+    /// Reads the entries of the directory at `path`, so they are visited next
+    fn enter(&mut self, path: PathBuf, depth: usize) -> Result<(), IoError> {
+        let canonical = if self.walk.symlinks == Symlinks::Follow {
+            let canonical = fs::canonicalize(&path)
+                .map_err(|err| IoError::new(Operation::Metadata, &path, err))?;
+
+            let is_loop = self
+                .stack
+                .iter()
+                .any(|frame| frame.canonical.as_ref() == Some(&canonical));
+
+            if is_loop {
+                return Err(IoError::new(
+                    Operation::Read,
+                    &path,
+                    io::Error::other(format!(
+                        "the directory is a symlink loop back to {}",
+                        EscapedPath(&canonical)
+                    )),
+                ));
+            }
+
+            Some(canonical)
+        } else {
+            None
+        };
+
+        let error = |err| IoError::new(Operation::Read, &path, err);
+        let mut entries = fs::read_dir(&path)
+            .map_err(error)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(error)?;
+        entries.sort();
+
+        self.stack.push(DirFrame {
+            entries: entries.into_iter(),
+            depth,
+            canonical,
+            #[cfg(feature = "ignore")]
+            ignore: if self.walk.ignore_files {
+                load_ignore_files(&path)?
+            } else {
+                None
+            },
+        });
+
+        Ok(())
+    }
+
+    #[cfg(feature = "ignore")]
+    fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        // the innermost ignore file which matches decides
+        self.stack
+            .iter()
+            .rev()
+            .filter_map(|frame| frame.ignore.as_ref())
+            .map(|ignore| ignore.matched(path, is_dir))
+            .find(|matched| !matched.is_none())
+            .is_some_and(|matched| matched.is_ignore())
+    }
+
+    #[cfg(not(feature = "ignore"))]
+    fn is_ignored(&self, _path: &Path, _is_dir: bool) -> bool {
+        false
+    }
+}
+
+/// Reads the `.gitignore` and `.ignore` files of the directory at `dir`
+#[cfg(feature = "ignore")]
+fn load_ignore_files(dir: &Path) -> Result<Option<ignore::gitignore::Gitignore>, IoError> {
+    let mut builder = ignore::gitignore::GitignoreBuilder::new(dir);
+    let mut found = None;
+
+    // later files take precedence
+    for name in [".gitignore", ".ignore"] {
+        let path = dir.join(name);
+        if path.is_file() {
+            if let Some(err) = builder.add(&path) {
+                return Err(IoError::new(Operation::Read, path, io::Error::other(err)));
+            }
+            found = Some(path);
+        }
+    }
+
+    let Some(path) = found else {
+        return Ok(None);
+    };
+
+    builder
+        .build()
+        .map(Some)
+        .map_err(|err| IoError::new(Operation::Read, path, io::Error::other(err)))
+}
+
+impl Iterator for WalkIter {
+    type Item = Result<NamedFile, IoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.started {
+            self.started = true;
+            if let Err(err) = self.enter(self.walk.root.clone(), 0) {
+                return Some(Err(err));
+            }
+        }
+
+        loop {
+            let frame = self.stack.last_mut()?;
+            let Some(path) = frame.entries.next() else {
+                self.stack.pop();
+                continue;
+            };
+            let depth = frame.depth + 1;
+
+            match self.visit(path, depth) {
+                Ok(Some(file)) => return Some(Ok(file)),
+                Ok(None) => (),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+impl clap::builder::TypedValueParser for NamedDirParser {
+    type Value = NamedDir;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        self.parse(cmd, arg, value.into())
+    }
+
+    fn parse(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: OsString,
+    ) -> Result<Self::Value, clap::Error> {
+        let path = Path::new(&value);
+
+        let metadata = fs::metadata(path).map_err(|err| crate::open_error(cmd, arg, path, err))?;
+
+        check_kind(FileKind::Dir, metadata.file_type())
+            .map_err(|reason| crate::validation_error(cmd, arg, path, reason))?;
+
+        // make sure the directory can be listed, so walking it won't fail right away
+        fs::read_dir(path).map_err(|err| crate::open_error(cmd, arg, path, err))?;
+
+        Ok(NamedDir { path: value.into() })
+    }
+}
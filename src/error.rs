@@ -0,0 +1,191 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// The operation which caused an [`IoError`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Operation {
+    Open,
+    Metadata,
+    Read,
+    Write,
+    Flush,
+    Seek,
+    Sync,
+    Rename,
+    Serialize,
+    Map,
+    Decode,
+}
+
+impl Operation {
+    fn describe(self) -> &'static str {
+        match self {
+            Self::Open => "opening",
+            Self::Metadata => "reading the metadata of",
+            Self::Read => "reading",
+            Self::Write => "writing",
+            Self::Flush => "flushing",
+            Self::Seek => "seeking",
+            Self::Sync => "syncing",
+            Self::Rename => "renaming",
+            Self::Serialize => "serializing",
+            Self::Map => "mapping",
+            Self::Decode => "decoding",
+        }
+    }
+}
+
+/// a wrapper around [`io::Error`] which knows which file it came from,
+/// and what was being done with that file
+///
+/// ```rust
+/// # use std::io;
+/// # use clap_file::{IoError, Operation};
+/// let err = IoError::new(Operation::Read, "data.bin", io::ErrorKind::UnexpectedEof.into());
+/// assert_eq!(err.operation(), Operation::Read);
+/// assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+/// assert!(err.to_string().contains("while reading the file at data.bin"));
+/// ```
+#[derive(Debug)]
+pub struct IoError {
+    operation: Operation,
+    path: PathBuf,
+    err: io::Error,
+}
+
+impl IoError {
+    pub fn new(operation: Operation, path: impl Into<PathBuf>, err: io::Error) -> Self {
+        Self {
+            operation,
+            path: path.into(),
+            err,
+        }
+    }
+
+    pub fn operation(&self) -> Operation {
+        self.operation
+    }
+
+    /// The path of the file, exactly as it was given
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn kind(&self) -> io::ErrorKind {
+        self.err.kind()
+    }
+
+    pub fn io_error(&self) -> &io::Error {
+        &self.err
+    }
+
+    pub fn into_parts(self) -> (Operation, PathBuf, io::Error) {
+        (self.operation, self.path, self.err)
+    }
+}
+
+impl From<IoError> for io::Error {
+    #[inline]
+    fn from(value: IoError) -> Self {
+        value.err
+    }
+}
+
+impl core::fmt::Display for IoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Encountered an error while {} the file at {}: {}",
+            self.operation.describe(),
+            EscapedPath(&self.path),
+            self.err
+        )
+    }
+}
+
+impl std::error::Error for IoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.err)
+    }
+}
+
+/// An error about one of the files of an argument, like a file matched by a glob pattern
+///
+/// The error is displayed after the context, which tells which file it is about.
+#[derive(Debug)]
+pub(crate) struct EntryError {
+    context: String,
+    err: io::Error,
+}
+
+impl EntryError {
+    /// Wraps `err` with its context, keeping its kind
+    pub(crate) fn wrap(context: String, err: io::Error) -> io::Error {
+        io::Error::new(err.kind(), Self { context, err })
+    }
+}
+
+impl core::fmt::Display for EntryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.context, self.err)
+    }
+}
+
+impl std::error::Error for EntryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.err)
+    }
+}
+
+/// Displays a path without losing information, bytes which aren't valid UTF-8 are escaped as `\xNN`
+///
+/// Backslashes are escaped as `\\`, so the escapes can't be confused with
+/// paths which contain the text `\xNN`.
+pub(crate) struct EscapedPath<'a>(pub &'a Path);
+
+impl core::fmt::Display for EscapedPath<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for chunk in self.0.as_os_str().as_encoded_bytes().utf8_chunks() {
+            let mut parts = chunk.valid().split('\\');
+            f.write_str(parts.next().unwrap_or_default())?;
+
+            for part in parts {
+                write!(f, "\\\\{part}")?;
+            }
+
+            for byte in chunk.invalid() {
+                write!(f, "\\x{byte:02X}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escaped_paths() {
+        let escaped = |path: &str| EscapedPath(Path::new(path)).to_string();
+
+        assert_eq!(escaped("dir/file.txt"), "dir/file.txt");
+        assert_eq!(escaped("ünïcode"), "ünïcode");
+        assert_eq!(escaped(r"a\b"), r"a\\b");
+        assert_eq!(escaped(r"\xFF"), r"\\xFF");
+        assert_eq!(escaped(r"\"), r"\\");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn escaped_invalid_bytes() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(std::ffi::OsStr::from_bytes(b"a\xFF\\\xFEb"));
+        assert_eq!(EscapedPath(path).to_string(), r"a\xFF\\\xFEb");
+    }
+}
@@ -0,0 +1,231 @@
+use std::{ffi::OsString, fs, ops::Deref, path::PathBuf};
+
+use clap::builder::TypedValueParser;
+
+use crate::{error::EscapedPath, NamedFile, NamedFileParser};
+
+/// The characters which make an argument a glob pattern
+const GLOB_CHARS: &[char] = &['*', '?', '['];
+
+/// This represents all the files matched by a glob pattern
+///
+/// Shells expand patterns like `src/**/*.json` before passing them on, but
+/// scripts, CI configurations or quoted arguments pass them on literally. These
+/// patterns are expanded into the files they match, sorted by path and without
+/// duplicates. A pattern which matches nothing is an error, just like a missing
+/// file. Arguments without any of `*`, `?` or `[` are opened as a single file.
+///
+/// Every matched file is opened and validated by the wrapped [`NamedFileParser`].
+/// Matches which aren't regular files, like directories, are skipped unless the
+/// parser asks for a [`kind`](NamedFileParser::kind) of file explicitly.
+///
+/// ```rust
+/// # use clap::Parser;
+/// # use clap_file::NamedFiles;
+/// #[derive(clap::Parser)]
+/// struct CliArgs {
+///     inputs: Vec<NamedFiles>,
+/// }
+///
+/// let args = CliArgs::try_parse_from(["prog", "src/*.rs", "Cargo.toml", "src/lib.rs"]).unwrap();
+///
+/// // the files of all arguments, sorted and without duplicates
+/// let inputs: NamedFiles = args.inputs.into_iter().collect();
+/// assert!(inputs.iter().any(|file| file.path().ends_with("lib.rs")));
+/// assert!(inputs.windows(2).all(|files| files[0].path() < files[1].path()));
+///
+/// assert!(CliArgs::try_parse_from(["prog", "src/*.missing"]).is_err());
+/// ```
+#[derive(Clone, Default)]
+pub struct NamedFiles {
+    files: Vec<NamedFile>,
+}
+
+/// A clap parser for parsing [`NamedFiles`]
+///
+/// Files are opened with the wrapped [`NamedFileParser`]
+#[derive(Clone, Debug)]
+pub struct NamedFilesParser {
+    files: NamedFileParser,
+    expand: bool,
+}
+
+impl clap::builder::ValueParserFactory for NamedFiles {
+    type Parser = NamedFilesParser;
+
+    #[inline]
+    fn value_parser() -> Self::Parser {
+        NamedFilesParser::new()
+    }
+}
+
+impl Default for NamedFilesParser {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NamedFilesParser {
+    /// Creates a parser which expands glob patterns
+    #[inline]
+    pub fn new() -> Self {
+        NamedFileParser::new().into()
+    }
+
+    /// Whether glob patterns are expanded, this is enabled by default
+    ///
+    /// Disable this to accept literal paths which contain `*`, `?` or `[`
+    #[inline]
+    pub fn expand(mut self, expand: bool) -> Self {
+        self.expand = expand;
+        self
+    }
+}
+
+impl From<NamedFileParser> for NamedFilesParser {
+    #[inline]
+    fn from(files: NamedFileParser) -> Self {
+        Self {
+            files,
+            expand: true,
+        }
+    }
+}
+
+impl NamedFiles {
+    pub fn into_vec(self) -> Vec<NamedFile> {
+        self.files
+    }
+}
+
+impl Deref for NamedFiles {
+    type Target = [NamedFile];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.files
+    }
+}
+
+impl From<NamedFiles> for Vec<NamedFile> {
+    #[inline]
+    fn from(files: NamedFiles) -> Self {
+        files.files
+    }
+}
+
+impl IntoIterator for NamedFiles {
+    type Item = NamedFile;
+    type IntoIter = std::vec::IntoIter<NamedFile>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.files.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a NamedFiles {
+    type Item = &'a NamedFile;
+    type IntoIter = std::slice::Iter<'a, NamedFile>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.files.iter()
+    }
+}
+
+/// Combines the files of several arguments, sorted by path and without duplicates
+impl FromIterator<NamedFiles> for NamedFiles {
+    fn from_iter<I: IntoIterator<Item = NamedFiles>>(iter: I) -> Self {
+        let mut files: Vec<_> = iter.into_iter().flatten().collect();
+        files.sort_by(|a, b| a.path().cmp(b.path()));
+        files.dedup_by(|a, b| a.path() == b.path());
+        Self { files }
+    }
+}
+
+impl NamedFilesParser {
+    /// The paths matched by `pattern`, sorted and without duplicates
+    fn expand_pattern(&self, pattern: &str) -> Result<Vec<PathBuf>, String> {
+        let options = glob::MatchOptions {
+            case_sensitive: true,
+            require_literal_separator: true,
+            require_literal_leading_dot: true,
+        };
+
+        let mut paths = glob::glob_with(pattern, options)
+            .map_err(|err| format!("invalid glob pattern: {err}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| {
+                format!(
+                    "could not expand the pattern at {}: {}",
+                    EscapedPath(err.path()),
+                    err.error()
+                )
+            })?;
+
+        paths.sort();
+        paths.dedup();
+        Ok(paths)
+    }
+}
+
+impl TypedValueParser for NamedFilesParser {
+    type Value = NamedFiles;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        self.parse(cmd, arg, value.into())
+    }
+
+    fn parse(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: OsString,
+    ) -> Result<Self::Value, clap::Error> {
+        let pattern = match value.to_str() {
+            Some(pattern) if self.expand && pattern.contains(GLOB_CHARS) => pattern,
+            _ => {
+                let file = self.files.parse(cmd, arg, value)?;
+                return Ok(NamedFiles { files: vec![file] });
+            }
+        };
+
+        let mut paths = self
+            .expand_pattern(pattern)
+            .map_err(|reason| crate::value_error(cmd, arg, &value, reason))?;
+
+        // patterns like `src/*` also match directories, which are only
+        // wanted when the parser asks for a kind of file explicitly
+        if self.files.get_kind().is_none() {
+            paths.retain(|path| fs::metadata(path).is_ok_and(|metadata| metadata.is_file()));
+        }
+
+        if paths.is_empty() {
+            return Err(crate::value_error(
+                cmd,
+                arg,
+                &value,
+                "the pattern did not match any files",
+            ));
+        }
+
+        let files = paths
+            .into_iter()
+            .map(|path| {
+                // errors are reported for the pattern, since that is what the user wrote
+                self.files.open_validated(&path).map_err(|err| {
+                    crate::entry_error(cmd, arg, &value, EscapedPath(&path), &path, err)
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(NamedFiles { files })
+    }
+}
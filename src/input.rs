@@ -0,0 +1,269 @@
+use std::{
+    io::{self, Read},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use crate::{
+    text::TextOptions, Codec, Decompression, IoError, LimitedReader, NamedFile, NamedFileParser,
+    Operation,
+};
+
+/// The path reported for inputs which read from stdin
+const STDIN_PATH: &str = "<stdin>";
+
+/// Set while any [`NamedInput`] holds on to stdin, in any command of the process
+static STDIN_CLAIMED: AtomicBool = AtomicBool::new(false);
+
+/// A claim on stdin, which is released once the last [`NamedInput`] using it is dropped
+#[derive(Debug)]
+struct StdinClaim {
+    /// set once stdin has been read, since it can't be read a second time
+    consumed: AtomicBool,
+}
+
+impl StdinClaim {
+    fn acquire() -> Option<Self> {
+        STDIN_CLAIMED
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| StdinClaim {
+                consumed: AtomicBool::new(false),
+            })
+    }
+
+    fn consume(&self) -> io::Result<()> {
+        if self.consumed.swap(true, Ordering::AcqRel) {
+            Err(crate::already_read_error())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Drop for StdinClaim {
+    fn drop(&mut self) {
+        STDIN_CLAIMED.store(false, Ordering::Release);
+    }
+}
+
+/// This represents an input, which is either a named file or stdin
+///
+/// A value of `-` is interpreted as stdin, anything else is opened as a file.
+/// Only one argument may claim stdin at a time, so specifying `-` for several
+/// arguments in one command is an error.
+///
+/// The claim is held for the whole process rather than for a single command,
+/// since stdin can only be read once. It is released once the `NamedInput`
+/// and all of its clones are dropped. Until then parsing `-` fails everywhere
+/// else in the process, like in a second parse, in [`try_update_from`](clap::Parser::try_update_from)
+/// or in tests running in parallel.
+///
+/// Stdin is only decompressed if the [`NamedFileParser`] forces a codec with
+/// [`Decompression::Force`], since it has no extension and can't be sniffed
+/// without consuming it.
+///
+/// This can be used with clap's derive API like so
+///
+/// ```rust
+/// # use clap::Parser;
+/// # use clap_file::NamedInput;
+/// #[derive(clap::Parser)]
+/// struct CliArgs {
+///     input: NamedInput,
+///     other: Option<NamedInput>,
+/// }
+///
+/// let args = CliArgs::try_parse_from(["prog", "-"]).unwrap();
+/// assert!(args.input.is_stdin());
+///
+/// // stdin is still claimed by the first parse
+/// assert!(CliArgs::try_parse_from(["prog", "-"]).is_err());
+/// drop(args);
+///
+/// assert!(CliArgs::try_parse_from(["prog", "-"]).is_ok());
+/// assert!(CliArgs::try_parse_from(["prog", "-", "-"]).is_err());
+/// ```
+#[derive(Clone)]
+pub struct NamedInput {
+    source: InputSource,
+}
+
+#[derive(Clone)]
+enum InputSource {
+    File(NamedFile),
+    Stdin {
+        claim: Arc<StdinClaim>,
+        codec: Option<Codec>,
+        limit: Option<u64>,
+        text: TextOptions,
+    },
+}
+
+/// A clap parser for parsing [`NamedInputs`](NamedInput)
+///
+/// Files are opened with the wrapped [`NamedFileParser`]
+#[derive(Clone, Debug, Default)]
+pub struct NamedInputParser {
+    files: NamedFileParser,
+}
+
+impl clap::builder::ValueParserFactory for NamedInput {
+    type Parser = NamedInputParser;
+
+    #[inline]
+    fn value_parser() -> Self::Parser {
+        NamedInputParser::new()
+    }
+}
+
+impl NamedInputParser {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl From<NamedFileParser> for NamedInputParser {
+    #[inline]
+    fn from(files: NamedFileParser) -> Self {
+        Self { files }
+    }
+}
+
+impl From<NamedFile> for NamedInput {
+    #[inline]
+    fn from(file: NamedFile) -> Self {
+        Self {
+            source: InputSource::File(file),
+        }
+    }
+}
+
+impl NamedInput {
+    pub fn read(&self) -> Result<Vec<u8>, IoError> {
+        match &self.source {
+            InputSource::File(file) => file.read(),
+            InputSource::Stdin {
+                claim,
+                codec,
+                limit,
+                ..
+            } => self.read_stdin(claim, *codec, *limit),
+        }
+    }
+
+    pub fn read_to_string(&self) -> Result<String, IoError> {
+        match &self.source {
+            InputSource::File(file) => file.read_to_string(),
+            InputSource::Stdin {
+                claim,
+                codec,
+                limit,
+                text,
+            } => text
+                .decode(self.read_stdin(claim, *codec, *limit)?)
+                .map_err(|err| self.error(Operation::Decode, err)),
+        }
+    }
+
+    /// The path of the file, or `<stdin>` if this input reads from stdin
+    pub fn path(&self) -> &Path {
+        match &self.source {
+            InputSource::File(file) => file.path(),
+            InputSource::Stdin { .. } => Path::new(STDIN_PATH),
+        }
+    }
+
+    pub fn is_stdin(&self) -> bool {
+        matches!(self.source, InputSource::Stdin { .. })
+    }
+
+    /// The underlying file, or `None` if this input reads from stdin
+    pub fn as_named_file(&self) -> Option<&NamedFile> {
+        match &self.source {
+            InputSource::File(file) => Some(file),
+            InputSource::Stdin { .. } => None,
+        }
+    }
+
+    /// Reads all of stdin, which can only be done once
+    fn read_stdin(
+        &self,
+        claim: &StdinClaim,
+        codec: Option<Codec>,
+        limit: Option<u64>,
+    ) -> Result<Vec<u8>, IoError> {
+        let error = |err| self.error(Operation::Read, err);
+
+        claim.consume().map_err(error)?;
+
+        let stdin: Box<dyn Read> = Box::new(io::stdin().lock());
+        let stdin = match codec {
+            Some(codec) => codec.decoder(stdin).map_err(error)?,
+            None => stdin,
+        };
+
+        let mut output = Vec::new();
+        match limit {
+            Some(limit) => LimitedReader::new(stdin, limit).read_to_end(&mut output),
+            None => { stdin }.read_to_end(&mut output),
+        }
+        .map_err(error)?;
+
+        Ok(output)
+    }
+
+    fn error(&self, operation: Operation, err: io::Error) -> IoError {
+        IoError::new(operation, self.path(), err)
+    }
+}
+
+impl clap::builder::TypedValueParser for NamedInputParser {
+    type Value = NamedInput;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        self.parse(cmd, arg, value.into())
+    }
+
+    fn parse(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: std::ffi::OsString,
+    ) -> Result<Self::Value, clap::Error> {
+        if value != "-" {
+            return self.files.parse(cmd, arg, value).map(NamedInput::from);
+        }
+
+        let claim = StdinClaim::acquire().ok_or_else(|| {
+            crate::value_error(
+                cmd,
+                arg,
+                &value,
+                "stdin is already used by another argument",
+            )
+        })?;
+
+        Ok(NamedInput {
+            source: InputSource::Stdin {
+                claim: Arc::new(claim),
+                codec: match self.files.get_decompression() {
+                    Decompression::Force(codec) => Some(codec),
+                    // stdin can't be peeked at, and has no extension
+                    Decompression::Auto | Decompression::Disabled => None,
+                },
+                limit: self.files.get_read_limit(),
+                text: self.files.get_text_options(),
+            },
+        })
+    }
+}
@@ -0,0 +1,197 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use crate::{IoError, NamedFile, NamedFileParser, Operation};
+
+/// This represents a named file which is only opened when it is used
+///
+/// Opening every file while parsing the arguments can exhaust the available
+/// file descriptors, when a program is given thousands of files (like `*.log`).
+/// A `LazyNamedFile` is validated while parsing, so missing files and files
+/// which violate the rules of the [`NamedFileParser`] are still reported like
+/// any other argument error, but the file is only opened by [`open`](LazyNamedFile::open).
+///
+/// ```rust
+/// # use clap::Parser;
+/// # use clap_file::LazyNamedFile;
+/// #[derive(clap::Parser)]
+/// struct CliArgs {
+///     inputs: Vec<LazyNamedFile>,
+/// }
+///
+/// let args = CliArgs::try_parse_from(["prog", "Cargo.toml", "src/lib.rs"]).unwrap();
+///
+/// for input in &args.inputs {
+///     assert!(!input.is_open());
+///     let contents = input.open().unwrap().read().unwrap();
+///     assert!(!contents.is_empty());
+///
+///     // release the file descriptor before opening the next file
+///     input.close();
+/// }
+///
+/// assert!(CliArgs::try_parse_from(["prog", "missing.txt"]).is_err());
+/// ```
+pub struct LazyNamedFile {
+    path: PathBuf,
+    parser: Arc<NamedFileParser>,
+    file: Mutex<Option<NamedFile>>,
+}
+
+/// A clap parser for parsing [`LazyNamedFiles`](LazyNamedFile)
+///
+/// Files are validated and later opened with the wrapped [`NamedFileParser`],
+/// only rules which depend on the file's metadata are checked while parsing.
+#[derive(Clone, Debug, Default)]
+pub struct LazyNamedFileParser {
+    files: Arc<NamedFileParser>,
+}
+
+impl clap::builder::ValueParserFactory for LazyNamedFile {
+    type Parser = LazyNamedFileParser;
+
+    #[inline]
+    fn value_parser() -> Self::Parser {
+        LazyNamedFileParser::new()
+    }
+}
+
+impl LazyNamedFileParser {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl From<NamedFileParser> for LazyNamedFileParser {
+    #[inline]
+    fn from(files: NamedFileParser) -> Self {
+        Self {
+            files: Arc::new(files),
+        }
+    }
+}
+
+impl Clone for LazyNamedFile {
+    fn clone(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            parser: self.parser.clone(),
+            file: Mutex::new(self.opened().clone()),
+        }
+    }
+}
+
+impl LazyNamedFile {
+    /// Opens the file, or returns the file if it is already open
+    ///
+    /// The file stays open until [`close`](LazyNamedFile::close) is called,
+    /// or the `LazyNamedFile` is dropped
+    pub fn open(&self) -> Result<NamedFile, IoError> {
+        let mut file = self.opened();
+
+        if let Some(file) = &*file {
+            return Ok(file.clone());
+        }
+
+        let opened = self
+            .parser
+            .open(&self.path)
+            .map_err(|err| IoError::new(Operation::Open, &self.path, err))?;
+        *file = Some(opened.clone());
+        Ok(opened)
+    }
+
+    /// Closes the file, so it can be opened again later
+    ///
+    /// The file descriptor is only released once all the [`NamedFiles`](NamedFile)
+    /// returned by [`open`](LazyNamedFile::open) have been dropped as well
+    pub fn close(&self) {
+        self.opened().take();
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.opened().is_some()
+    }
+
+    /// The path of the file, exactly as it was given
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn opened(&self) -> std::sync::MutexGuard<'_, Option<NamedFile>> {
+        self.file.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Raises the soft limit on open file descriptors (`RLIMIT_NOFILE`) to the hard limit
+///
+/// This is useful for programs which need to keep many files open at the same time.
+/// Returns the new soft limit.
+///
+/// ```rust
+/// let limit = clap_file::raise_fd_limit().unwrap();
+/// assert!(limit > 0);
+/// ```
+#[cfg(unix)]
+pub fn raise_fd_limit() -> std::io::Result<u64> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+
+    // SAFETY: `limit` is a valid `rlimit` to write the limits into
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    // macOS rejects limits above `OPEN_MAX`, even if the hard limit is unlimited
+    #[cfg(target_os = "macos")]
+    let target = limit.rlim_max.min(libc::OPEN_MAX as libc::rlim_t);
+    #[cfg(not(target_os = "macos"))]
+    let target = limit.rlim_max;
+
+    if limit.rlim_cur < target {
+        limit.rlim_cur = target;
+
+        // SAFETY: `limit` is a valid `rlimit`
+        if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &limit) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+
+    // `rlim_t` isn't `u64` on every platform
+    #[allow(clippy::unnecessary_cast)]
+    Ok(limit.rlim_cur as u64)
+}
+
+impl clap::builder::TypedValueParser for LazyNamedFileParser {
+    type Value = LazyNamedFile;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        self.parse(cmd, arg, value.into())
+    }
+
+    fn parse(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: OsString,
+    ) -> Result<Self::Value, clap::Error> {
+        self.files.validate_path(cmd, arg, Path::new(&value))?;
+
+        Ok(LazyNamedFile {
+            path: value.into(),
+            parser: self.files.clone(),
+            file: Mutex::new(None),
+        })
+    }
+}
@@ -1,9 +1,72 @@
 use std::{
+    ffi::OsStr,
     fs,
     io::{self, Read},
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError,
+    },
 };
 
+mod argfile;
+mod atomic;
+mod compress;
+mod dir;
+mod error;
+#[cfg(feature = "glob")]
+mod files;
+mod input;
+mod lazy;
+mod lines;
+mod list;
+#[cfg(feature = "mmap")]
+mod mmap;
+mod optional;
+mod output;
+mod parser;
+mod path;
+mod suggest;
+mod text;
+#[cfg(any(feature = "json", feature = "toml", feature = "yaml"))]
+mod typed;
+
+pub use argfile::ArgFiles;
+pub use atomic::{AtomicOutputFile, AtomicOutputFileParser};
+pub use compress::{Codec, Compression, Decompression};
+pub use dir::{NamedDir, NamedDirParser, Symlinks, Walk, WalkIter};
+pub use error::{IoError, Operation};
+#[cfg(feature = "glob")]
+pub use files::{NamedFiles, NamedFilesParser};
+pub use input::{NamedInput, NamedInputParser};
+#[cfg(unix)]
+pub use lazy::raise_fd_limit;
+pub use lazy::{LazyNamedFile, LazyNamedFileParser};
+pub use lines::{Delimiter, Line, Lines, Record, RecordError, Records};
+pub use list::{FileList, FileListParser};
+#[cfg(feature = "mmap")]
+pub use mmap::FileBytes;
+pub use optional::{OptionalNamedFile, OptionalNamedFileParser, Origin};
+pub use output::{NamedOutputFile, NamedOutputFileParser, OutputMode};
+pub use parser::{FileKind, NamedFileParser};
+pub use path::{
+    ExistingDir, ExistingDirParser, ExistingPath, ExistingPathParser, NonExistingPath,
+    NonExistingPathParser,
+};
+pub use text::Encoding;
+
+use text::TextOptions;
+#[cfg(any(feature = "json", feature = "toml", feature = "yaml"))]
+pub use typed::{
+    ConfigFile, ConfigFileParser, DeserializeError, Format, TypedOutputFile, TypedOutputFileParser,
+};
+#[cfg(feature = "json")]
+pub use typed::{JsonFile, JsonFileParser};
+#[cfg(feature = "toml")]
+pub use typed::{TomlFile, TomlFileParser};
+#[cfg(feature = "yaml")]
+pub use typed::{YamlFile, YamlFileParser};
+
 /// This represents a named file
 ///
 /// This can be used with clap's derive API like so
@@ -15,137 +78,427 @@ use std::{
 ///     input: NamedFile,
 /// }
 /// ```
+///
+/// [`read`](NamedFile::read) and [`read_to_string`](NamedFile::read_to_string)
+/// always read the whole file from the start, regardless of the file's cursor.
+/// Files which can't be seeked (like pipes) can only be read once.
+///
+/// Cloning a `NamedFile` is cheap, all clones share the same underlying file handle.
+///
+/// Compressed files are transparently decompressed by [`read`](NamedFile::read),
+/// [`read_to_string`](NamedFile::read_to_string) and [`reader`](NamedFile::reader)
+/// if the feature for their [`Codec`] is enabled. This is configured with
+/// [`NamedFileParser::decompression`].
+///
+/// ```rust
+/// # use clap::Parser;
+/// # use clap_file::NamedFile;
+/// # #[derive(clap::Parser)]
+/// # struct CliArgs {
+/// #     input: NamedFile,
+/// # }
+/// let args = CliArgs::try_parse_from(["prog", "Cargo.toml"]).unwrap();
+/// let first = args.input.read_to_string().unwrap();
+/// assert_eq!(first, args.input.read_to_string().unwrap());
+/// ```
+#[derive(Clone)]
 pub struct NamedFile {
-    pub file: fs::File,
-    pub path: PathBuf,
+    handle: Arc<FileHandle>,
+    path: PathBuf,
+    codec: Option<Codec>,
+    limit: Option<u64>,
+    text: TextOptions,
 }
 
-/// A clap parser for parsing [`NamedFiles`](NamedFile)
-#[derive(Copy, Clone, Debug)]
-pub struct NamedFileParser;
+/// The file handle shared between all clones of a [`NamedFile`]
+struct FileHandle {
+    file: fs::File,
+    /// set once a file which can't be seeked has been read
+    consumed: AtomicBool,
+}
 
-impl clap::builder::ValueParserFactory for NamedFile {
-    type Parser = NamedFileParser;
+impl NamedFile {
+    pub fn new(file: fs::File, path: impl Into<PathBuf>) -> Self {
+        Self {
+            handle: Arc::new(FileHandle {
+                file,
+                consumed: AtomicBool::new(false),
+            }),
+            path: path.into(),
+            codec: None,
+            limit: None,
+            text: TextOptions::default(),
+        }
+    }
 
-    #[inline]
-    fn value_parser() -> Self::Parser {
-        NamedFileParser
+    /// Decompress the file with `codec` when reading it
+    pub fn with_codec(mut self, codec: Option<Codec>) -> Self {
+        self.codec = codec;
+        self
     }
-}
 
-/// a wrapper around [`io::Error`] which knows which file it came from
-#[derive(Debug)]
-pub struct IoError {
-    path: PathBuf,
-    err: io::Error,
-}
+    /// Fail reads which would return more than `limit` bytes
+    ///
+    /// For compressed files, this limits the size of the decompressed contents
+    pub fn with_limit(mut self, limit: Option<u64>) -> Self {
+        self.limit = limit;
+        self
+    }
 
-impl From<IoError> for io::Error {
-    #[inline]
-    fn from(value: IoError) -> Self {
-        value.err
+    /// Decode files without a byte order mark with `encoding` in
+    /// [`read_to_string`](NamedFile::read_to_string), instead of UTF-8
+    pub fn with_encoding(mut self, encoding: Option<Encoding>) -> Self {
+        self.text.encoding = encoding;
+        self
     }
-}
 
-impl core::fmt::Display for IoError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "Encountered an error while opening the file at {}: {}",
-            self.path.display(),
-            self.err
-        )
+    /// Replace invalid text with U+FFFD in [`read_to_string`](NamedFile::read_to_string),
+    /// instead of failing
+    pub fn with_lossy(mut self, lossy: bool) -> Self {
+        self.text.lossy = lossy;
+        self
     }
-}
 
-impl std::error::Error for IoError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        Some(&self.err)
+    /// Replace `\r\n` with `\n` in [`read_to_string`](NamedFile::read_to_string)
+    pub fn with_normalized_newlines(mut self, normalize: bool) -> Self {
+        self.text.normalize_newlines = normalize;
+        self
     }
-}
 
-impl NamedFile {
     pub fn read(&self) -> Result<Vec<u8>, IoError> {
-        let size = self.file().metadata().map_or(0, |metadata| metadata.len());
-        let mut output = Vec::with_capacity(size as usize);
-        io::BufReader::new(self.file())
-            .read_to_end(&mut output)
-            .map_err(|err| IoError {
-                path: self.path.clone(),
-                err,
-            })?;
-        Ok(output)
+        self.read_with(|reader, size| {
+            let mut output = Vec::with_capacity(size);
+            reader.read_to_end(&mut output)?;
+            Ok(output)
+        })
     }
 
+    /// Reads the whole file and decodes it as text
+    ///
+    /// A byte order mark selects the encoding and is removed, otherwise the
+    /// file is decoded as UTF-8, or with the [`Encoding`] configured by
+    /// [`NamedFileParser::encoding`].
     pub fn read_to_string(&self) -> Result<String, IoError> {
-        let size = self.file().metadata().map_or(0, |metadata| metadata.len());
-        let mut output = String::with_capacity(size as usize);
-        io::BufReader::new(self.file())
-            .read_to_string(&mut output)
-            .map_err(|err| IoError {
-                path: self.path.clone(),
-                err,
-            })?;
-        Ok(output)
+        let bytes = self.read()?;
+        self.text
+            .decode(bytes)
+            .map_err(|err| IoError::new(Operation::Decode, &self.path, err))
+    }
+
+    /// A reader over the whole file, which decompresses the file if it has a [`Codec`]
+    ///
+    /// Like [`read`](NamedFile::read), this always starts at the beginning of the file
+    pub fn reader(&self) -> Result<NamedFileReader<'_>, IoError> {
+        let error = |err| IoError::new(Operation::Read, &self.path, err);
+
+        let raw = self.raw_reader().map_err(error)?;
+        let inner = match self.codec {
+            Some(codec) => codec.decoder(raw).map_err(error)?,
+            None => raw,
+        };
+        let inner = match self.limit {
+            Some(limit) => Box::new(LimitedReader::new(inner, limit)),
+            None => inner,
+        };
+
+        Ok(NamedFileReader {
+            inner,
+            path: &self.path,
+        })
+    }
+
+    /// A reader over the whole file, without decompressing it
+    fn raw_reader(&self) -> io::Result<Box<dyn Read + '_>> {
+        let seekable = self
+            .file()
+            .metadata()
+            .is_ok_and(|metadata| metadata.is_file());
+
+        if seekable {
+            Ok(Box::new(PositionedReader {
+                file: self.file(),
+                offset: 0,
+            }))
+        } else if self.handle.consumed.swap(true, Ordering::AcqRel) {
+            Err(already_read_error())
+        } else {
+            Ok(Box::new(self.file()))
+        }
+    }
+
+    /// Calls `f` with a reader over the whole file, and the expected size of the output
+    fn read_with<T>(
+        &self,
+        f: impl FnOnce(&mut dyn Read, usize) -> io::Result<T>,
+    ) -> Result<T, IoError> {
+        let size = match self.codec {
+            // the size of the decompressed output isn't known
+            Some(_) => 0,
+            None => self.file().metadata().map_or(0, |metadata| metadata.len()),
+        };
+        // don't trust the metadata with allocating more than the file may contain
+        let size = self.limit.map_or(size, |limit| size.min(limit));
+
+        let mut reader = self.reader()?;
+        f(&mut reader, size as usize).map_err(|err| IoError::new(Operation::Read, &self.path, err))
     }
 
     pub fn file(&self) -> &fs::File {
-        &self.file
+        &self.handle.file
     }
 
+    /// The path of the file, this is the compressed file's path even if it is decompressed
     pub fn path(&self) -> &Path {
         &self.path
     }
+
+    /// The codec the file is decompressed with
+    pub fn codec(&self) -> Option<Codec> {
+        self.codec
+    }
+
+    /// The maximum number of bytes which may be read from the file
+    pub fn limit(&self) -> Option<u64> {
+        self.limit
+    }
+}
+
+/// A streaming reader over the contents of a [`NamedFile`], see [`NamedFile::reader`]
+pub struct NamedFileReader<'a> {
+    inner: Box<dyn Read + 'a>,
+    path: &'a Path,
+}
+
+impl NamedFileReader<'_> {
+    pub fn path(&self) -> &Path {
+        self.path
+    }
+}
+
+impl Read for NamedFileReader<'_> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
 }
 
-// This shouldn't be necessary, but `clap` requires it
-impl Clone for NamedFile {
-    fn clone(&self) -> Self {
+/// Reads a file starting at a given offset, without moving the file's cursor
+struct PositionedReader<'a> {
+    file: &'a fs::File,
+    offset: u64,
+}
+
+impl Read for PositionedReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = read_at(self.file, buf, self.offset)?;
+        self.offset += read as u64;
+        Ok(read)
+    }
+}
+
+/// Fails once more than `limit` bytes are read from the wrapped reader
+pub(crate) struct LimitedReader<R> {
+    inner: R,
+    limit: u64,
+    remaining: u64,
+}
+
+impl<R> LimitedReader<R> {
+    pub(crate) fn new(inner: R, limit: u64) -> Self {
         Self {
-            file: self.file.try_clone().unwrap(),
-            path: self.path.clone(),
+            inner,
+            limit,
+            remaining: limit,
         }
     }
 }
 
-impl clap::builder::TypedValueParser for NamedFileParser {
-    type Value = NamedFile;
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
 
-    fn parse_ref(
-        &self,
-        cmd: &clap::Command,
-        arg: Option<&clap::Arg>,
-        value: &std::ffi::OsStr,
-    ) -> Result<Self::Value, clap::Error> {
-        self.parse(cmd, arg, value.into())
+        if self.remaining == 0 {
+            // the input may end exactly at the limit, which is fine
+            return match self.inner.read(&mut [0])? {
+                0 => Ok(0),
+                _ => Err(limit_error(self.limit)),
+            };
+        }
+
+        let len = buf
+            .len()
+            .min(self.remaining.try_into().unwrap_or(usize::MAX));
+        let read = self.inner.read(&mut buf[..len])?;
+        self.remaining -= read as u64;
+        Ok(read)
     }
+}
 
-    fn parse(
-        &self,
-        cmd: &clap::Command,
-        arg: Option<&clap::Arg>,
-        value: std::ffi::OsString,
-    ) -> Result<Self::Value, clap::Error> {
-        let path = Path::new(&value);
-        let file = std::fs::File::open(path).map_err(|err| match arg {
-            Some(arg) => clap::Error::raw(
-                clap::error::ErrorKind::ValueValidation,
-                format_args!(
-                    "Could not open file specified at {}: {path:?}\n{err}\n",
-                    arg.get_value_names().unwrap()[0],
-                ),
-            )
-            .with_cmd(cmd),
-            _ => clap::Error::raw(
-                clap::error::ErrorKind::ValueValidation,
-                format_args!("Could not open file: {path:?}\n{err}\n"),
-            )
-            .with_cmd(cmd),
-        })?;
-
-        Ok(NamedFile {
-            file,
-            path: value.into(),
-        })
+#[cfg(unix)]
+pub(crate) fn read_at(file: &fs::File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    std::os::unix::fs::FileExt::read_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+pub(crate) fn read_at(file: &fs::File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    std::os::windows::fs::FileExt::seek_read(file, buf, offset)
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn read_at(mut file: &fs::File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    io::Seek::seek(&mut file, io::SeekFrom::Start(offset))?;
+    file.read(buf)
+}
+
+/// Converts bytes read from a file into an `OsString`, which must be UTF-8
+/// on platforms other than Unix
+#[cfg(unix)]
+pub(crate) fn os_string_from_bytes(bytes: Vec<u8>) -> io::Result<std::ffi::OsString> {
+    Ok(std::os::unix::ffi::OsStringExt::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn os_string_from_bytes(bytes: Vec<u8>) -> io::Result<std::ffi::OsString> {
+    String::from_utf8(bytes)
+        .map(Into::into)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.utf8_error()))
+}
+
+/// The directory containing `path`, which is `.` for bare file names
+pub(crate) fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
     }
 }
+
+/// The error reported when a source which can't be seeked is read a second time
+pub(crate) fn already_read_error() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "the file can't be seeked and has already been read",
+    )
+}
+
+/// The error reported when a file is larger than the configured limit
+pub(crate) fn limit_error(limit: u64) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::FileTooLarge,
+        format!("file exceeds limit of {limit} bytes"),
+    )
+}
+
+/// Creates the error reported when a file argument could not be opened
+pub(crate) fn open_error(
+    cmd: &clap::Command,
+    arg: Option<&clap::Arg>,
+    path: &Path,
+    err: io::Error,
+) -> clap::Error {
+    let hints = suggest::Hints::for_open_error(path, &err);
+    with_hints(value_error(cmd, arg, path.as_os_str(), err), hints)
+}
+
+/// Creates the error reported when the file at `path` was found through the
+/// argument `value`, like a glob pattern or a list of files, but was rejected
+///
+/// The error is reported for `value`, since that is what the user wrote, and
+/// `context` tells which of its files was rejected
+pub(crate) fn entry_error(
+    cmd: &clap::Command,
+    arg: Option<&clap::Arg>,
+    value: &OsStr,
+    context: impl core::fmt::Display,
+    path: &Path,
+    err: parser::FileError,
+) -> clap::Error {
+    let context = context.to_string();
+
+    match err {
+        parser::FileError::Open(err) => {
+            let hints = suggest::Hints::for_open_error(path, &err);
+            let err = error::EntryError::wrap(context, err);
+            with_hints(value_error(cmd, arg, value, err), hints)
+        }
+        parser::FileError::Invalid(reason) => {
+            value_error(cmd, arg, value, format!("{context}: {reason}"))
+        }
+        parser::FileError::Unusable(err) => {
+            value_error(cmd, arg, value, error::EntryError::wrap(context, err))
+        }
+    }
+}
+
+/// Adds the suggestions for a file which could not be opened to `err`
+fn with_hints(mut err: clap::Error, hints: suggest::Hints) -> clap::Error {
+    use clap::error::{ContextKind, ContextValue};
+
+    if !hints.similar.is_empty() {
+        err.insert(
+            ContextKind::SuggestedValue,
+            ContextValue::Strings(hints.similar),
+        );
+    }
+
+    if !hints.tips.is_empty() {
+        err.insert(
+            ContextKind::Suggested,
+            ContextValue::StyledStrs(hints.tips.into_iter().map(Into::into).collect()),
+        );
+    }
+
+    err
+}
+
+/// Creates the error reported when a file argument was opened, but failed validation
+pub(crate) fn validation_error(
+    cmd: &clap::Command,
+    arg: Option<&clap::Arg>,
+    path: &Path,
+    reason: impl core::fmt::Display,
+) -> clap::Error {
+    value_error(cmd, arg, path.as_os_str(), reason.to_string())
+}
+
+/// Creates an [`ErrorKind::ValueValidation`](clap::error::ErrorKind::ValueValidation) error
+/// for an invalid `value`, which is rendered exactly like clap's own errors
+///
+/// `err` is the error's source, and explains why the value is invalid
+pub(crate) fn value_error(
+    cmd: &clap::Command,
+    arg: Option<&clap::Arg>,
+    value: &OsStr,
+    err: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+) -> clap::Error {
+    use clap::builder::TypedValueParser;
+
+    // clap doesn't expose a way to attach a source to an error, other than
+    // failing inside of one of its own value parsers. That also fills in the
+    // `InvalidArg` and `InvalidValue` context, using the same formatting for
+    // the argument as clap uses everywhere else.
+    let err = Arc::new(Mutex::new(Some(err.into())));
+    let parser = clap::builder::OsStringValueParser::new().try_map(move |_| {
+        Err::<(), _>(
+            err.lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take()
+                .expect("the error is only taken once"),
+        )
+    });
+
+    match parser.parse_ref(cmd, arg, value) {
+        Err(err) => err,
+        Ok(()) => unreachable!("the parser always fails"),
+    }
+}
+
+/// A fresh temporary directory for the test called `name`
+#[cfg(test)]
+pub(crate) fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("clap-file-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
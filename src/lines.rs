@@ -0,0 +1,362 @@
+use std::{
+    fmt, io,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use crate::{error::EscapedPath, IoError, NamedFile, NamedFileReader};
+
+/// The size of the buffer records are searched in, it grows to fit longer records
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// What separates the records of a file
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Delimiter {
+    /// `\n`, a `\r` before the `\n` is removed as well
+    Newline,
+    /// `\0`, as produced by `find -print0`
+    Nul,
+    /// Any other byte
+    Byte(u8),
+}
+
+impl Delimiter {
+    pub(crate) fn byte(self) -> u8 {
+        match self {
+            Self::Newline => b'\n',
+            Self::Nul => b'\0',
+            Self::Byte(byte) => byte,
+        }
+    }
+}
+
+/// A record of a file, see [`NamedFile::records`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    /// The 1-based number of the record
+    pub number: u64,
+    /// The offset of the first byte of the record from the start of the file
+    pub offset: u64,
+    /// The contents of the record, without the delimiter
+    pub bytes: Vec<u8>,
+}
+
+/// A line of a file, see [`NamedFile::lines`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Line {
+    /// The 1-based line number
+    pub number: u64,
+    /// The offset of the first byte of the line from the start of the file
+    pub offset: u64,
+    /// The contents of the line, without the line ending
+    pub text: String,
+}
+
+/// The error reported when a record of a file could not be read
+#[derive(Debug)]
+pub struct RecordError {
+    path: PathBuf,
+    number: u64,
+    offset: u64,
+    err: io::Error,
+}
+
+impl RecordError {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The 1-based number of the record (or line) which could not be read
+    pub fn number(&self) -> u64 {
+        self.number
+    }
+
+    /// The offset of the first byte of the record which could not be read
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn kind(&self) -> io::ErrorKind {
+        self.err.kind()
+    }
+
+    pub fn io_error(&self) -> &io::Error {
+        &self.err
+    }
+}
+
+impl From<RecordError> for io::Error {
+    #[inline]
+    fn from(value: RecordError) -> Self {
+        value.err
+    }
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Encountered an error while reading line {} of the file at {}: {}",
+            self.number,
+            EscapedPath(&self.path),
+            self.err
+        )
+    }
+}
+
+impl std::error::Error for RecordError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.err)
+    }
+}
+
+/// An iterator over the records of a file, see [`NamedFile::records`]
+pub struct Records<'a> {
+    reader: NamedFileReader<'a>,
+    delimiter: Delimiter,
+    buffer: Vec<u8>,
+    /// The start of the unread data in `buffer`
+    start: usize,
+    /// The end of the unread data in `buffer`
+    end: usize,
+    number: u64,
+    offset: u64,
+    done: bool,
+}
+
+/// An iterator over the lines of a file, see [`NamedFile::lines`]
+pub struct Lines<'a> {
+    records: Records<'a>,
+}
+
+impl NamedFile {
+    /// An iterator over the records of the file, which are separated by `delimiter`
+    ///
+    /// Like [`read`](NamedFile::read), this starts at the beginning of the file
+    /// and decompresses the file if it has a [`Codec`](crate::Codec).
+    pub fn records(&self, delimiter: Delimiter) -> Result<Records<'_>, IoError> {
+        Ok(Records {
+            reader: self.reader()?,
+            delimiter,
+            buffer: vec![0; BUFFER_SIZE],
+            start: 0,
+            end: 0,
+            number: 0,
+            offset: 0,
+            done: false,
+        })
+    }
+
+    /// An iterator over the lines of the file, which may end in `\n` or `\r\n`
+    ///
+    /// ```rust
+    /// # use clap::Parser;
+    /// # use clap_file::NamedFile;
+    /// #[derive(clap::Parser)]
+    /// struct CliArgs {
+    ///     input: NamedFile,
+    /// }
+    ///
+    /// let args = CliArgs::try_parse_from(["prog", "Cargo.toml"]).unwrap();
+    /// let first = args.input.lines().unwrap().next().unwrap().unwrap();
+    /// assert_eq!(first.number, 1);
+    /// assert_eq!(first.offset, 0);
+    /// assert_eq!(first.text, "[package]");
+    /// ```
+    pub fn lines(&self) -> Result<Lines<'_>, IoError> {
+        Ok(Lines {
+            records: self.records(Delimiter::Newline)?,
+        })
+    }
+}
+
+impl Records<'_> {
+    fn error(&self, err: io::Error) -> RecordError {
+        RecordError {
+            path: self.reader.path().into(),
+            number: self.number + 1,
+            offset: self.offset,
+            err,
+        }
+    }
+
+    /// Takes the record in `start..end` of the buffer, and skips the delimiter after it
+    fn take(&mut self, end: usize, delimited: bool) -> Record {
+        let mut bytes = self.buffer[self.start..end].to_vec();
+        let consumed = end - self.start + usize::from(delimited);
+
+        if self.delimiter == Delimiter::Newline && delimited && bytes.last() == Some(&b'\r') {
+            bytes.pop();
+        }
+
+        self.number += 1;
+        let record = Record {
+            number: self.number,
+            offset: self.offset,
+            bytes,
+        };
+
+        self.start += consumed;
+        self.offset += consumed as u64;
+        record
+    }
+
+    /// Reads more data into the buffer, returning false at the end of the file
+    fn fill(&mut self) -> io::Result<bool> {
+        if self.start > 0 {
+            self.buffer.copy_within(self.start..self.end, 0);
+            self.end -= self.start;
+            self.start = 0;
+        }
+
+        if self.end == self.buffer.len() {
+            self.buffer.resize(self.buffer.len() * 2, 0);
+        }
+
+        loop {
+            match self.reader.read(&mut self.buffer[self.end..]) {
+                Ok(0) => return Ok(false),
+                Ok(read) => {
+                    self.end += read;
+                    return Ok(true);
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+impl Iterator for Records<'_> {
+    type Item = Result<Record, RecordError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut searched = self.start;
+
+        loop {
+            let delimiter = self.delimiter.byte();
+            if let Some(index) = memchr::memchr(delimiter, &self.buffer[searched..self.end]) {
+                return Some(Ok(self.take(searched + index, true)));
+            }
+
+            // the data before `searched` doesn't have to be searched again
+            let unsearched = self.end - self.start;
+
+            match self.fill() {
+                Ok(true) => searched = self.start + unsearched,
+                Ok(false) => {
+                    self.done = true;
+                    return (self.start < self.end).then(|| Ok(self.take(self.end, false)));
+                }
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(self.error(err)));
+                }
+            }
+        }
+    }
+}
+
+impl std::iter::FusedIterator for Records<'_> {}
+
+impl Iterator for Lines<'_> {
+    type Item = Result<Line, RecordError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = match self.records.next()? {
+            Ok(record) => record,
+            Err(err) => return Some(Err(err)),
+        };
+
+        match String::from_utf8(record.bytes) {
+            Ok(text) => Some(Ok(Line {
+                number: record.number,
+                offset: record.offset,
+                text,
+            })),
+            Err(err) => {
+                self.records.done = true;
+                Some(Err(RecordError {
+                    path: self.records.reader.path().into(),
+                    number: record.number,
+                    offset: record.offset,
+                    err: io::Error::new(io::ErrorKind::InvalidData, err.utf8_error()),
+                }))
+            }
+        }
+    }
+}
+
+impl std::iter::FusedIterator for Lines<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    /// Reads the records of a temporary file containing `contents`
+    fn read_records(name: &str, contents: &[u8], delimiter: Delimiter) -> Vec<Record> {
+        let path = std::env::temp_dir().join(format!("clap-file-{}-{name}", std::process::id()));
+        fs::write(&path, contents).unwrap();
+
+        let file = NamedFile::new(fs::File::open(&path).unwrap(), &path);
+        let records = file.records(delimiter).unwrap().collect::<Result<_, _>>();
+        fs::remove_file(&path).unwrap();
+        records.unwrap()
+    }
+
+    #[test]
+    fn records_longer_than_the_buffer() {
+        let long = vec![b'a'; BUFFER_SIZE * 3 + 5];
+        let contents = [b"x\n".as_slice(), &long, b"\ny"].concat();
+
+        let records = read_records("long-records", &contents, Delimiter::Newline);
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1].number, 2);
+        assert_eq!(records[1].offset, 2);
+        assert_eq!(records[1].bytes, long);
+        assert_eq!(records[2].offset, 2 + long.len() as u64 + 1);
+        assert_eq!(records[2].bytes, b"y");
+    }
+
+    #[test]
+    fn records_ending_at_the_end_of_the_buffer() {
+        let full = vec![b'a'; BUFFER_SIZE - 1];
+        let contents = [full.as_slice(), b"\0b\0"].concat();
+
+        let records = read_records("full-buffer", &contents, Delimiter::Nul);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].bytes, full);
+        assert_eq!(records[1].offset, BUFFER_SIZE as u64);
+        assert_eq!(records[1].bytes, b"b");
+    }
+
+    #[test]
+    fn crlf_split_across_a_refill() {
+        // the `\r` is the last byte of the first read, the `\n` the first byte of the next
+        let line = vec![b'a'; BUFFER_SIZE - 1];
+        let contents = [line.as_slice(), b"\r\nb\r\n"].concat();
+
+        let records = read_records("split-crlf", &contents, Delimiter::Newline);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].bytes, line);
+        assert_eq!(records[1].offset, BUFFER_SIZE as u64 + 1);
+        assert_eq!(records[1].bytes, b"b");
+    }
+
+    #[test]
+    fn carriage_returns_are_only_removed_before_newlines() {
+        let records = read_records("carriage-returns", b"a\r\nb\r", Delimiter::Newline);
+        let bytes: Vec<_> = records.into_iter().map(|record| record.bytes).collect();
+        assert_eq!(bytes, [b"a".as_slice(), b"b\r"]);
+
+        let records = read_records("other-delimiter", b"a\r,b", Delimiter::Byte(b','));
+        let bytes: Vec<_> = records.into_iter().map(|record| record.bytes).collect();
+        assert_eq!(bytes, [b"a\r".as_slice(), b"b"]);
+    }
+}
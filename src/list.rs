@@ -0,0 +1,268 @@
+use std::{ffi::OsString, ops::Deref, path::Path};
+
+use clap::builder::TypedValueParser;
+
+use crate::{
+    error::EscapedPath, text::TextOptions, Delimiter, NamedFile, NamedFileParser, NamedInput,
+    NamedInputParser,
+};
+
+/// This represents the files listed in a file, like `tar -T` or `rsync --files-from`
+///
+/// The list is read from the given file, or from stdin if the value is `-`.
+/// Entries are separated by newlines by default, empty lines and lines starting
+/// with `#` are skipped. Relative entries are resolved against the directory of
+/// the list file (or the current directory for stdin).
+///
+/// Every listed file is opened and validated by the wrapped [`NamedFileParser`].
+/// The list itself is read with the parser's read limit, decompression and
+/// text options. A list read from stdin keeps stdin claimed for as long as
+/// the `FileList` lives.
+///
+/// ```rust
+/// # use clap::Parser;
+/// # use clap_file::{Delimiter, FileList, FileListParser};
+/// #[derive(clap::Parser)]
+/// struct CliArgs {
+///     #[arg(long)]
+///     files_from: Option<FileList>,
+///     #[arg(long, value_parser = FileListParser::new().delimiter(Delimiter::Nul))]
+///     files_from0: Option<FileList>,
+/// }
+///
+/// # let dir = std::env::temp_dir().join(format!("clap-file-list-{}", std::process::id()));
+/// # std::fs::create_dir_all(&dir).unwrap();
+/// # std::fs::write(dir.join("a.txt"), "").unwrap();
+/// let list = dir.join("inputs.txt");
+/// std::fs::write(&list, "# inputs\na.txt\n\n").unwrap();
+///
+/// let args = ["prog".as_ref(), "--files-from".as_ref(), list.as_os_str()];
+/// let files = CliArgs::try_parse_from(args).unwrap().files_from.unwrap();
+/// assert_eq!(files.len(), 1);
+/// assert_eq!(files[0].path(), dir.join("a.txt"));
+///
+/// std::fs::write(&list, "a.txt\nmissing.txt\n").unwrap();
+/// let err = CliArgs::try_parse_from(args).err().unwrap();
+/// assert!(err.to_string().contains("inputs.txt:2: missing.txt"));
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct FileList {
+    files: Vec<NamedFile>,
+    list: NamedInput,
+}
+
+/// A clap parser for parsing [`FileLists`](FileList)
+///
+/// Listed files are opened with the wrapped [`NamedFileParser`]
+#[derive(Clone, Debug)]
+pub struct FileListParser {
+    files: NamedFileParser,
+    delimiter: Delimiter,
+}
+
+impl clap::builder::ValueParserFactory for FileList {
+    type Parser = FileListParser;
+
+    #[inline]
+    fn value_parser() -> Self::Parser {
+        FileListParser::new()
+    }
+}
+
+impl Default for FileListParser {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileListParser {
+    /// Creates a parser for lists of newline separated paths
+    #[inline]
+    pub fn new() -> Self {
+        NamedFileParser::new().into()
+    }
+
+    /// What separates the entries of the list, by default [`Delimiter::Newline`]
+    ///
+    /// Only newline separated lists may contain comments, other lists
+    /// just skip empty entries. Use [`Delimiter::Nul`] for lists produced by
+    /// `find -print0`, which may contain any path.
+    #[inline]
+    pub fn delimiter(mut self, delimiter: Delimiter) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+}
+
+impl From<NamedFileParser> for FileListParser {
+    #[inline]
+    fn from(files: NamedFileParser) -> Self {
+        Self {
+            files,
+            delimiter: Delimiter::Newline,
+        }
+    }
+}
+
+impl FileList {
+    /// The path of the list, or `<stdin>` if it was read from stdin
+    pub fn path(&self) -> &Path {
+        self.list.path()
+    }
+
+    pub fn into_vec(self) -> Vec<NamedFile> {
+        self.files
+    }
+}
+
+impl Deref for FileList {
+    type Target = [NamedFile];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.files
+    }
+}
+
+impl From<FileList> for Vec<NamedFile> {
+    #[inline]
+    fn from(list: FileList) -> Self {
+        list.files
+    }
+}
+
+impl IntoIterator for FileList {
+    type Item = NamedFile;
+    type IntoIter = std::vec::IntoIter<NamedFile>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.files.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a FileList {
+    type Item = &'a NamedFile;
+    type IntoIter = std::slice::Iter<'a, NamedFile>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.files.iter()
+    }
+}
+
+impl FileListParser {
+    /// Whether the entry should be skipped, because it is empty or a comment
+    fn is_skipped(&self, entry: &[u8]) -> bool {
+        match self.delimiter {
+            Delimiter::Newline => {
+                entry.iter().all(u8::is_ascii_whitespace) || entry.starts_with(b"#")
+            }
+            Delimiter::Nul | Delimiter::Byte(_) => entry.is_empty(),
+        }
+    }
+}
+
+impl TypedValueParser for FileListParser {
+    type Value = FileList;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        self.parse(cmd, arg, value.into())
+    }
+
+    fn parse(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: OsString,
+    ) -> Result<Self::Value, clap::Error> {
+        let list =
+            NamedInputParser::from(self.files.reading_options()).parse_ref(cmd, arg, &value)?;
+
+        // lists are split as bytes, so paths which aren't valid UTF-8 survive,
+        // unless the parser asks for the text to be decoded
+        let contents = if self.files.get_text_options() == TextOptions::default() {
+            list.read()
+        } else {
+            list.read_to_string().map(String::into_bytes)
+        }
+        .map_err(|err| crate::value_error(cmd, arg, &value, err))?;
+
+        let base = list.as_named_file().and_then(|file| file.path().parent());
+        let context = |number: usize| format!("{}:{number}", EscapedPath(list.path()));
+
+        let mut files = Vec::new();
+
+        for (index, mut entry) in contents
+            .split(|&byte| byte == self.delimiter.byte())
+            .enumerate()
+        {
+            let number = index + 1;
+
+            if self.delimiter == Delimiter::Newline {
+                entry = entry.strip_suffix(b"\r").unwrap_or(entry);
+            }
+
+            if self.is_skipped(entry) {
+                continue;
+            }
+
+            let entry = crate::os_string_from_bytes(entry.to_vec()).map_err(|err| {
+                let reason = format!("{}: {err}", context(number));
+                crate::value_error(cmd, arg, &value, reason)
+            })?;
+            let entry = Path::new(&entry);
+            let path = match base {
+                Some(base) => base.join(entry),
+                None => entry.to_path_buf(),
+            };
+
+            let file = self.files.open_validated(&path).map_err(|err| {
+                let context = format!("{}: {}", context(number), EscapedPath(entry));
+                crate::entry_error(cmd, arg, &value, context, &path, err)
+            })?;
+            files.push(file);
+        }
+
+        Ok(FileList { files, list })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn entries_which_are_not_utf8_are_escaped() {
+        let dir = crate::test_dir("list-escaped");
+        let list = dir.join("inputs");
+        fs::write(&list, b"missing\xFF.txt\0").unwrap();
+
+        let parser = FileListParser::new().delimiter(Delimiter::Nul);
+        let err = parser
+            .parse_ref(&clap::Command::new("prog"), None, list.as_os_str())
+            .err()
+            .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let message = err.to_string();
+        assert!(
+            message.contains("inputs:1: missing\\xFF.txt: "),
+            "{message}"
+        );
+
+        let source = std::error::Error::source(&err).unwrap();
+        let source = source.downcast_ref::<std::io::Error>().unwrap();
+        assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,123 @@
+use std::{io, ops::Deref};
+
+use crate::{IoError, NamedFile, Operation};
+
+/// Files smaller than this are read instead of mapped, since mapping has a fixed overhead
+const MAP_THRESHOLD: u64 = 64 * 1024;
+
+/// The contents of a [`NamedFile`], which are either mapped into memory or read into a buffer
+///
+/// ```rust
+/// # use clap::Parser;
+/// # use clap_file::{FileBytes, NamedFile};
+/// #[derive(clap::Parser)]
+/// struct CliArgs {
+///     input: NamedFile,
+/// }
+///
+/// let args = CliArgs::try_parse_from(["prog", "Cargo.toml"]).unwrap();
+/// let bytes = args.input.read_bytes().unwrap();
+/// assert!(bytes.starts_with(b"[package]"));
+/// ```
+pub enum FileBytes {
+    Mapped(memmap2::Mmap),
+    Read(Vec<u8>),
+}
+
+impl Deref for FileBytes {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Mapped(map) => map,
+            Self::Read(bytes) => bytes,
+        }
+    }
+}
+
+impl AsRef<[u8]> for FileBytes {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl NamedFile {
+    /// Maps the file into memory, read-only
+    ///
+    /// Only regular files can be mapped. Compressed files are mapped as they
+    /// are, without decompressing them.
+    ///
+    /// # Truncation
+    ///
+    /// The mapping reflects changes other processes make to the file. If the
+    /// file is truncated while it is mapped, accessing the truncated part of
+    /// the mapping will crash the process (with `SIGBUS` on Unix). To catch
+    /// files which are being modified, the length of the file is checked again
+    /// after mapping it, and an error is returned if it changed. This can't
+    /// prevent the file from being truncated later, so only map files which
+    /// aren't expected to change while the program runs.
+    pub fn map(&self) -> Result<memmap2::Mmap, IoError> {
+        let error = |err| IoError::new(Operation::Map, self.path(), err);
+
+        let metadata = self
+            .file()
+            .metadata()
+            .map_err(|err| IoError::new(Operation::Metadata, self.path(), err))?;
+
+        if !metadata.is_file() {
+            return Err(error(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only regular files can be mapped",
+            )));
+        }
+
+        if let Some(limit) = self.limit() {
+            if metadata.len() > limit {
+                return Err(error(crate::limit_error(limit)));
+            }
+        }
+
+        // SAFETY: the file may be modified while it is mapped, which is documented above,
+        // and the length of the file is checked again below to detect concurrent modifications
+        let map = unsafe { memmap2::Mmap::map(self.file()) }.map_err(error)?;
+
+        let len = self
+            .file()
+            .metadata()
+            .map_err(|err| IoError::new(Operation::Metadata, self.path(), err))?
+            .len();
+
+        if len != map.len() as u64 {
+            return Err(error(io::Error::other(format!(
+                "the file changed size while it was being mapped ({} bytes mapped, now {len} bytes)",
+                map.len()
+            ))));
+        }
+
+        Ok(map)
+    }
+
+    /// Reads the whole file, by mapping it into memory if that is worthwhile
+    ///
+    /// Large regular files are mapped (see [`map`](NamedFile::map) for the
+    /// caveats), everything else (small files, pipes, compressed files) is read
+    /// like [`read`](NamedFile::read) does. If mapping fails, this falls back to
+    /// reading the file.
+    pub fn read_bytes(&self) -> Result<FileBytes, IoError> {
+        let mappable = self.codec().is_none()
+            && self
+                .file()
+                .metadata()
+                .is_ok_and(|metadata| metadata.is_file() && metadata.len() >= MAP_THRESHOLD);
+
+        if mappable {
+            if let Ok(map) = self.map() {
+                return Ok(FileBytes::Mapped(map));
+            }
+        }
+
+        self.read().map(FileBytes::Read)
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    ffi::{OsStr, OsString},
+    io,
+    path::{Path, PathBuf},
+};
+
+use clap::{builder::TypedValueParser, parser::ValueSource};
+
+use crate::{NamedFile, NamedFileParser};
+
+/// This represents a named file which may be missing, if it wasn't given explicitly
+///
+/// This is meant for arguments with a default path, like "use `./project.toml`
+/// if it exists". If the default file is missing, there simply is no file. If a
+/// path is given explicitly, it must exist just like for a [`NamedFile`].
+///
+/// ```rust
+/// # use clap::Parser;
+/// # use clap_file::{Origin, OptionalNamedFile};
+/// #[derive(clap::Parser)]
+/// struct CliArgs {
+///     #[arg(long, default_value = "project.toml")]
+///     config: OptionalNamedFile,
+/// }
+///
+/// let args = CliArgs::try_parse_from(["prog"]).unwrap();
+/// assert!(args.config.file().is_none());
+/// assert_eq!(args.config.origin(), Origin::Default);
+///
+/// let args = CliArgs::try_parse_from(["prog", "--config", "Cargo.toml"]).unwrap();
+/// assert!(args.config.file().is_some());
+/// assert_eq!(args.config.origin(), Origin::User);
+///
+/// assert!(CliArgs::try_parse_from(["prog", "--config", "missing.toml"]).is_err());
+///
+/// // the default path is still an error when it is given explicitly
+/// assert!(CliArgs::try_parse_from(["prog", "--config", "project.toml"]).is_err());
+/// ```
+#[derive(Clone)]
+pub struct OptionalNamedFile {
+    file: Option<NamedFile>,
+    path: PathBuf,
+    origin: Origin,
+}
+
+/// Where the path of an [`OptionalNamedFile`] came from
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Origin {
+    /// The path was given on the command line (or by an environment variable)
+    User,
+    /// The path is one of the argument's default values
+    Default,
+}
+
+/// A clap parser for parsing [`OptionalNamedFiles`](OptionalNamedFile)
+///
+/// Files are opened with the wrapped [`NamedFileParser`]
+#[derive(Clone, Debug, Default)]
+pub struct OptionalNamedFileParser {
+    files: NamedFileParser,
+}
+
+impl clap::builder::ValueParserFactory for OptionalNamedFile {
+    type Parser = OptionalNamedFileParser;
+
+    #[inline]
+    fn value_parser() -> Self::Parser {
+        OptionalNamedFileParser::new()
+    }
+}
+
+impl OptionalNamedFileParser {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl From<NamedFileParser> for OptionalNamedFileParser {
+    #[inline]
+    fn from(files: NamedFileParser) -> Self {
+        Self { files }
+    }
+}
+
+impl OptionalNamedFile {
+    /// The file, or `None` if the default file doesn't exist
+    pub fn file(&self) -> Option<&NamedFile> {
+        self.file.as_ref()
+    }
+
+    pub fn into_file(self) -> Option<NamedFile> {
+        self.file
+    }
+
+    /// The path of the file, even if it doesn't exist
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn origin(&self) -> Origin {
+        self.origin
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.origin == Origin::Default
+    }
+}
+
+impl OptionalNamedFileParser {
+    fn parse_from_source(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &OsStr,
+        source: ValueSource,
+    ) -> Result<OptionalNamedFile, clap::Error> {
+        let origin = if source == ValueSource::DefaultValue {
+            Origin::Default
+        } else {
+            Origin::User
+        };
+
+        let file = match self.files.parse_ref(cmd, arg, value) {
+            Ok(file) => Some(file),
+            Err(err) if origin == Origin::Default && is_not_found(&err) => None,
+            Err(err) => return Err(err),
+        };
+
+        Ok(OptionalNamedFile {
+            file,
+            path: value.into(),
+            origin,
+        })
+    }
+}
+
+impl TypedValueParser for OptionalNamedFileParser {
+    type Value = OptionalNamedFile;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        // without a source, the value is treated as given by the user
+        self.parse_from_source(cmd, arg, value, ValueSource::CommandLine)
+    }
+
+    fn parse_ref_(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &OsStr,
+        source: ValueSource,
+    ) -> Result<Self::Value, clap::Error> {
+        self.parse_from_source(cmd, arg, value, source)
+    }
+
+    fn parse_(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: OsString,
+        source: ValueSource,
+    ) -> Result<Self::Value, clap::Error> {
+        self.parse_from_source(cmd, arg, &value, source)
+    }
+}
+
+/// Whether the error was caused by a missing file
+fn is_not_found(err: &clap::Error) -> bool {
+    std::error::Error::source(err)
+        .and_then(|source| source.downcast_ref::<io::Error>())
+        .is_some_and(|err| err.kind() == io::ErrorKind::NotFound)
+}
@@ -0,0 +1,345 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{compress::SharedEncoder, Compression, IoError, Operation};
+
+/// The path reported for outputs which write to stdout
+const STDOUT_PATH: &str = "<stdout>";
+
+/// This represents an output, which is either a named file or stdout
+///
+/// A value of `-` is interpreted as stdout, anything else is opened as a file
+/// for writing. How existing files are treated is configured by the parser's
+/// [`OutputMode`], by default they are truncated.
+///
+/// If the feature for a [`Codec`](crate::Codec) is enabled, and the file has
+/// its extension (like `out.ndjson.gz`), everything written is transparently
+/// compressed. The compressed stream is completed by [`finish`](NamedOutputFile::finish),
+/// or when the last clone of the output is dropped.
+///
+/// This can be used with clap's derive API like so
+///
+/// ```rust
+/// # use clap_file::{NamedOutputFile, NamedOutputFileParser, OutputMode};
+/// #[derive(clap::Parser)]
+/// struct CliArgs {
+///     #[arg(short, long)]
+///     output: NamedOutputFile,
+///     #[arg(long, value_parser = NamedOutputFileParser::new().mode(OutputMode::Append))]
+///     log: Option<NamedOutputFile>,
+/// }
+/// ```
+#[derive(Clone)]
+pub struct NamedOutputFile {
+    sink: OutputSink,
+    encoder: Option<Arc<SharedEncoder>>,
+}
+
+#[derive(Clone)]
+enum OutputSink {
+    File { file: Arc<fs::File>, path: PathBuf },
+    Stdout,
+}
+
+/// How a [`NamedOutputFileParser`] treats files which already exist
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub enum OutputMode {
+    /// Truncate the file if it already exists (the default)
+    #[default]
+    Truncate,
+    /// Append to the file if it already exists
+    Append,
+    /// Refuse to open the file if it already exists
+    CreateNew,
+}
+
+/// A clap parser for parsing [`NamedOutputFiles`](NamedOutputFile)
+#[derive(Copy, Clone, Debug)]
+pub struct NamedOutputFileParser {
+    mode: OutputMode,
+    compression: Compression,
+}
+
+impl clap::builder::ValueParserFactory for NamedOutputFile {
+    type Parser = NamedOutputFileParser;
+
+    #[inline]
+    fn value_parser() -> Self::Parser {
+        NamedOutputFileParser::new()
+    }
+}
+
+impl Default for NamedOutputFileParser {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NamedOutputFileParser {
+    /// Creates a parser which creates the file, or truncates it if it already exists
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            mode: OutputMode::Truncate,
+            compression: Compression::Auto,
+        }
+    }
+
+    /// How files which already exist are treated, by default they are truncated
+    #[inline]
+    pub const fn mode(mut self, mode: OutputMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// How files are compressed, by default the codec is picked from the extension
+    #[inline]
+    pub const fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    fn open_options(&self) -> fs::OpenOptions {
+        let mut options = fs::OpenOptions::new();
+        match self.mode {
+            OutputMode::Truncate => options.write(true).create(true).truncate(true),
+            OutputMode::Append => options.append(true).create(true),
+            OutputMode::CreateNew => options.write(true).create_new(true),
+        };
+        options
+    }
+}
+
+impl NamedOutputFile {
+    pub fn write_all(&self, bytes: &[u8]) -> Result<(), IoError> {
+        Write::write_all(&mut &*self, bytes).map_err(|err| self.error(Operation::Write, err))
+    }
+
+    pub fn flush(&self) -> Result<(), IoError> {
+        Write::flush(&mut &*self).map_err(|err| self.error(Operation::Flush, err))
+    }
+
+    /// Completes the compressed stream if the output is compressed, and flushes the output
+    ///
+    /// Nothing can be written to a compressed output after it was finished
+    pub fn finish(&self) -> Result<(), IoError> {
+        if let Some(encoder) = &self.encoder {
+            encoder
+                .finish()
+                .map_err(|err| self.error(Operation::Write, err))?;
+        }
+
+        self.flush_sink()
+            .map_err(|err| self.error(Operation::Flush, err))
+    }
+
+    fn flush_sink(&self) -> io::Result<()> {
+        match &self.sink {
+            OutputSink::File { file, .. } => Write::flush(&mut &**file),
+            OutputSink::Stdout => io::stdout().lock().flush(),
+        }
+    }
+
+    /// The underlying file, or `None` if this output writes to stdout
+    pub fn file(&self) -> Option<&fs::File> {
+        match &self.sink {
+            OutputSink::File { file, .. } => Some(file),
+            OutputSink::Stdout => None,
+        }
+    }
+
+    /// The path of the file, or `<stdout>` if this output writes to stdout
+    pub fn path(&self) -> &Path {
+        match &self.sink {
+            OutputSink::File { path, .. } => path,
+            OutputSink::Stdout => Path::new(STDOUT_PATH),
+        }
+    }
+
+    pub fn is_stdout(&self) -> bool {
+        matches!(self.sink, OutputSink::Stdout)
+    }
+
+    fn error(&self, operation: Operation, err: io::Error) -> IoError {
+        IoError::new(operation, self.path(), err)
+    }
+}
+
+impl Write for &NamedOutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(encoder) = &self.encoder {
+            return encoder.write(buf);
+        }
+
+        match &self.sink {
+            OutputSink::File { file, .. } => Write::write(&mut &**file, buf),
+            OutputSink::Stdout => io::stdout().lock().write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(encoder) = &self.encoder {
+            encoder.flush()?;
+        }
+
+        self.flush_sink()
+    }
+}
+
+impl Write for NamedOutputFile {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Write::write(&mut &*self, buf)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Write::flush(&mut &*self)
+    }
+}
+
+impl clap::builder::TypedValueParser for NamedOutputFileParser {
+    type Value = NamedOutputFile;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        self.parse(cmd, arg, value.into())
+    }
+
+    fn parse(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: std::ffi::OsString,
+    ) -> Result<Self::Value, clap::Error> {
+        let path = Path::new(&value);
+        let codec = self.compression.codec(path);
+
+        let (sink, writer): (_, Box<dyn Write + Send>) = if value == "-" {
+            (OutputSink::Stdout, Box::new(io::stdout()))
+        } else {
+            let file = self
+                .open_options()
+                .open(path)
+                .map_err(|err| crate::open_error(cmd, arg, path, err))?;
+            let file = Arc::new(file);
+
+            (
+                OutputSink::File {
+                    file: file.clone(),
+                    path: path.into(),
+                },
+                Box::new(file),
+            )
+        };
+
+        let encoder = codec
+            .map(|codec| SharedEncoder::new(codec, writer).map(Arc::new))
+            .transpose()
+            .map_err(|err| crate::open_error(cmd, arg, path, err))?;
+
+        Ok(NamedOutputFile { sink, encoder })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::builder::TypedValueParser;
+
+    use super::*;
+
+    fn parse(parser: NamedOutputFileParser, path: &Path) -> NamedOutputFile {
+        parser
+            .parse_ref(&clap::Command::new("prog"), None, path.as_os_str())
+            .unwrap()
+    }
+
+    #[test]
+    fn modes() {
+        let dir = crate::test_dir("output-modes");
+        let path = dir.join("out.txt");
+        fs::write(&path, "old\n").unwrap();
+
+        let append = NamedOutputFileParser::new().mode(OutputMode::Append);
+        parse(append, &path).write_all(b"new\n").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"old\nnew\n");
+
+        parse(NamedOutputFileParser::new(), &path)
+            .write_all(b"truncated\n")
+            .unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"truncated\n");
+
+        let create_new = NamedOutputFileParser::new().mode(OutputMode::CreateNew);
+        let err = create_new
+            .parse_ref(&clap::Command::new("prog"), None, path.as_os_str())
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("exists"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn compressed_outputs_are_finished_when_the_last_clone_is_dropped() {
+        let dir = crate::test_dir("output-gzip-drop");
+        let path = dir.join("out.txt.gz");
+
+        let output = parse(NamedOutputFileParser::new(), &path);
+        let clone = output.clone();
+        output.write_all(b"hello\n").unwrap();
+        drop(output);
+
+        // the stream isn't completed while a clone can still write to it
+        clone.write_all(b"world\n").unwrap();
+        clone.flush().unwrap();
+        drop(clone);
+
+        let file = crate::NamedFileParser::new().open(&path).unwrap();
+        assert_eq!(file.codec(), Some(crate::Codec::Gzip));
+        assert_eq!(file.read().unwrap(), b"hello\nworld\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn compressed_outputs_are_finished_by_finish() {
+        let dir = crate::test_dir("output-gzip-finish");
+        let path = dir.join("out.txt.gz");
+
+        let output = parse(NamedOutputFileParser::new(), &path);
+        output.write_all(b"hello\n").unwrap();
+        output.finish().unwrap();
+        assert!(output.write_all(b"too late").is_err());
+
+        let file = crate::NamedFileParser::new().open(&path).unwrap();
+        assert_eq!(file.read().unwrap(), b"hello\n");
+
+        // finishing twice does nothing
+        output.finish().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn compression_can_be_disabled() {
+        let dir = crate::test_dir("output-gzip-disabled");
+        let path = dir.join("out.txt.gz");
+
+        let parser = NamedOutputFileParser::new().compression(Compression::Disabled);
+        parse(parser, &path).write_all(b"plain").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"plain");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,772 @@
+use std::{fs, io, path::Path};
+
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+
+use crate::{text::TextOptions, Decompression, Encoding, NamedFile};
+
+/// A clap parser for parsing [`NamedFiles`](NamedFile)
+///
+/// By default files are opened read-only, but the parser can be configured
+/// like [`fs::OpenOptions`]
+///
+/// ```rust
+/// # use clap_file::{NamedFile, NamedFileParser};
+/// #[derive(clap::Parser)]
+/// struct CliArgs {
+///     #[arg(value_parser = NamedFileParser::new().read(true).write(true))]
+///     database: NamedFile,
+/// }
+/// ```
+///
+/// It can also validate the files it opens, and rejects any file which doesn't
+/// satisfy all of the configured rules
+///
+/// ```rust
+/// # use clap::Parser;
+/// # use clap_file::{FileKind, NamedFile, NamedFileParser};
+/// #[derive(clap::Parser)]
+/// struct CliArgs {
+///     #[arg(value_parser = NamedFileParser::new().kind(FileKind::File).extensions(["toml"]))]
+///     config: NamedFile,
+/// }
+///
+/// assert!(CliArgs::try_parse_from(["prog", "Cargo.toml"]).is_ok());
+/// assert!(CliArgs::try_parse_from(["prog", "src"]).is_err());
+/// assert!(CliArgs::try_parse_from(["prog", "src/lib.rs"]).is_err());
+/// ```
+///
+/// Errors are reported just like clap reports its own errors
+///
+/// ```rust
+/// # use clap::{Arg, Command, error::{ContextKind, ContextValue, ErrorKind}};
+/// # use clap_file::NamedFileParser;
+/// let cmd = Command::new("prog")
+///     .arg(Arg::new("input").long("input").value_parser(NamedFileParser::new()));
+///
+/// let err = cmd
+///     .try_get_matches_from(["prog", "--input", "missing.txt"])
+///     .unwrap_err();
+/// assert_eq!(err.kind(), ErrorKind::ValueValidation);
+/// assert_eq!(
+///     err.get(ContextKind::InvalidArg),
+///     Some(&ContextValue::String("--input <input>".into()))
+/// );
+///
+/// // the original `io::Error` is the source of the error
+/// let source = std::error::Error::source(&err).unwrap();
+/// let io_error = source.downcast_ref::<std::io::Error>().unwrap();
+/// assert_eq!(io_error.kind(), std::io::ErrorKind::NotFound);
+/// ```
+#[derive(Clone, Debug)]
+pub struct NamedFileParser {
+    options: fs::OpenOptions,
+    #[cfg(unix)]
+    custom_flags: i32,
+    extensions: Vec<String>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    kind: Option<FileKind>,
+    readable: bool,
+    #[cfg(unix)]
+    executable: bool,
+    decompression: Decompression,
+    read_limit: Option<u64>,
+    text: TextOptions,
+}
+
+/// The kinds of files a [`NamedFileParser`] can require
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FileKind {
+    /// A regular file
+    File,
+    Dir,
+    Fifo,
+    CharDevice,
+    BlockDevice,
+    Socket,
+}
+
+impl FileKind {
+    /// The kind of the given file type, or `None` if it is none of the known kinds
+    pub fn of(file_type: fs::FileType) -> Option<Self> {
+        if file_type.is_file() {
+            return Some(Self::File);
+        }
+
+        if file_type.is_dir() {
+            return Some(Self::Dir);
+        }
+
+        #[cfg(unix)]
+        {
+            if file_type.is_fifo() {
+                return Some(Self::Fifo);
+            }
+
+            if file_type.is_char_device() {
+                return Some(Self::CharDevice);
+            }
+
+            if file_type.is_block_device() {
+                return Some(Self::BlockDevice);
+            }
+
+            if file_type.is_socket() {
+                return Some(Self::Socket);
+            }
+        }
+
+        None
+    }
+}
+
+impl core::fmt::Display for FileKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::File => "a regular file",
+            Self::Dir => "a directory",
+            Self::Fifo => "a FIFO",
+            Self::CharDevice => "a character device",
+            Self::BlockDevice => "a block device",
+            Self::Socket => "a socket",
+        })
+    }
+}
+
+impl clap::builder::ValueParserFactory for NamedFile {
+    type Parser = NamedFileParser;
+
+    #[inline]
+    fn value_parser() -> Self::Parser {
+        NamedFileParser::new()
+    }
+}
+
+impl Default for NamedFileParser {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NamedFileParser {
+    /// Creates a parser which opens files read-only
+    pub fn new() -> Self {
+        let mut options = fs::OpenOptions::new();
+        options.read(true);
+        Self {
+            options,
+            #[cfg(unix)]
+            custom_flags: 0,
+            extensions: Vec::new(),
+            min_size: None,
+            max_size: None,
+            kind: None,
+            readable: false,
+            #[cfg(unix)]
+            executable: false,
+            decompression: Decompression::Auto,
+            read_limit: None,
+            text: TextOptions::default(),
+        }
+    }
+
+    /// See [`OpenOptions::read`](fs::OpenOptions::read)
+    pub fn read(mut self, read: bool) -> Self {
+        self.options.read(read);
+        self
+    }
+
+    /// See [`OpenOptions::write`](fs::OpenOptions::write)
+    pub fn write(mut self, write: bool) -> Self {
+        self.options.write(write);
+        self
+    }
+
+    /// See [`OpenOptions::append`](fs::OpenOptions::append)
+    pub fn append(mut self, append: bool) -> Self {
+        self.options.append(append);
+        self
+    }
+
+    /// See [`OpenOptions::truncate`](fs::OpenOptions::truncate)
+    pub fn truncate(mut self, truncate: bool) -> Self {
+        self.options.truncate(truncate);
+        self
+    }
+
+    /// See [`OpenOptions::create`](fs::OpenOptions::create)
+    pub fn create(mut self, create: bool) -> Self {
+        self.options.create(create);
+        self
+    }
+
+    /// See [`OpenOptions::create_new`](fs::OpenOptions::create_new)
+    pub fn create_new(mut self, create_new: bool) -> Self {
+        self.options.create_new(create_new);
+        self
+    }
+
+    /// The permissions a newly created file gets,
+    /// see [`OpenOptionsExt::mode`](std::os::unix::fs::OpenOptionsExt::mode)
+    #[cfg(unix)]
+    pub fn mode(mut self, mode: u32) -> Self {
+        std::os::unix::fs::OpenOptionsExt::mode(&mut self.options, mode);
+        self
+    }
+
+    /// Adds flags to pass to `open`,
+    /// see [`OpenOptionsExt::custom_flags`](std::os::unix::fs::OpenOptionsExt::custom_flags)
+    ///
+    /// Unlike `OpenOptionsExt::custom_flags` this adds to the flags which are already set,
+    /// so it can be combined with [`nofollow`](Self::nofollow) and [`nonblock`](Self::nonblock).
+    /// Note that the standard library always opens files with `O_CLOEXEC`.
+    #[cfg(unix)]
+    pub fn custom_flags(mut self, flags: i32) -> Self {
+        self.custom_flags |= flags;
+        std::os::unix::fs::OpenOptionsExt::custom_flags(&mut self.options, self.custom_flags);
+        self
+    }
+
+    /// Fail to open the file if it is a symlink (`O_NOFOLLOW`)
+    #[cfg(unix)]
+    pub fn nofollow(self) -> Self {
+        self.custom_flags(libc::O_NOFOLLOW)
+    }
+
+    /// Open the file in non-blocking mode (`O_NONBLOCK`)
+    ///
+    /// This is mostly useful to open FIFOs without waiting for a writer
+    #[cfg(unix)]
+    pub fn nonblock(self) -> Self {
+        self.custom_flags(libc::O_NONBLOCK)
+    }
+
+    /// Only accept files with one of the given extensions
+    ///
+    /// Extensions are compared case-insensitively and may contain dots, like `tar.gz`
+    pub fn extensions<I>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.extensions
+            .extend(extensions.into_iter().map(normalize_extension));
+        self
+    }
+
+    /// Only accept files which are at least `size` bytes large
+    pub fn min_size(mut self, size: u64) -> Self {
+        self.min_size = Some(size);
+        self
+    }
+
+    /// Only accept files which are at most `size` bytes large
+    pub fn max_size(mut self, size: u64) -> Self {
+        self.max_size = Some(size);
+        self
+    }
+
+    /// Only accept files of the given kind
+    pub fn kind(mut self, kind: FileKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    /// Only accept files which the current user may read
+    pub fn readable(mut self, readable: bool) -> Self {
+        self.readable = readable;
+        self
+    }
+
+    /// Only accept files which the current user may execute
+    #[cfg(unix)]
+    pub fn executable(mut self, executable: bool) -> Self {
+        self.executable = executable;
+        self
+    }
+
+    /// How files are decompressed, by default the codec is picked from the
+    /// extension or the first bytes of the file
+    pub fn decompression(mut self, decompression: Decompression) -> Self {
+        self.decompression = decompression;
+        self
+    }
+
+    /// The maximum number of bytes which may be read from a file
+    ///
+    /// Regular files which are larger are rejected while parsing, and reads
+    /// fail with [`ErrorKind::FileTooLarge`](std::io::ErrorKind::FileTooLarge)
+    /// once they exceed the limit. This protects against inputs like `/dev/zero`,
+    /// and limits the decompressed size of compressed files.
+    ///
+    /// ```rust
+    /// # use clap::Parser;
+    /// # use clap_file::{NamedFile, NamedFileParser};
+    /// #[derive(clap::Parser)]
+    /// struct CliArgs {
+    ///     #[arg(value_parser = NamedFileParser::new().read_limit(16))]
+    ///     input: NamedFile,
+    /// }
+    ///
+    /// assert!(CliArgs::try_parse_from(["prog", "Cargo.toml"]).is_err());
+    /// ```
+    pub fn read_limit(mut self, limit: u64) -> Self {
+        self.read_limit = Some(limit);
+        self
+    }
+
+    #[cfg(feature = "glob")]
+    pub(crate) fn get_kind(&self) -> Option<FileKind> {
+        self.kind
+    }
+
+    pub(crate) fn get_decompression(&self) -> Decompression {
+        self.decompression
+    }
+
+    pub(crate) fn get_read_limit(&self) -> Option<u64> {
+        self.read_limit
+    }
+
+    /// The encoding of files without a byte order mark, by default UTF-8
+    ///
+    /// Byte order marks are always honoured, so UTF-16 files with a byte order
+    /// mark are decoded regardless of this setting
+    ///
+    /// ```rust
+    /// # use clap_file::{Encoding, NamedFile, NamedFileParser};
+    /// #[derive(clap::Parser)]
+    /// struct CliArgs {
+    ///     #[arg(value_parser = NamedFileParser::new().encoding(Encoding::Utf16Le).lossy(true))]
+    ///     input: NamedFile,
+    /// }
+    /// ```
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.text.encoding = Some(encoding);
+        self
+    }
+
+    /// Replace invalid text with U+FFFD when decoding files, instead of failing
+    pub fn lossy(mut self, lossy: bool) -> Self {
+        self.text.lossy = lossy;
+        self
+    }
+
+    /// Replace `\r\n` with `\n` when decoding files
+    pub fn normalize_newlines(mut self, normalize: bool) -> Self {
+        self.text.normalize_newlines = normalize;
+        self
+    }
+
+    pub(crate) fn get_text_options(&self) -> TextOptions {
+        self.text
+    }
+
+    /// A parser which reads files just like this one, but without any of
+    /// the rules about which files are accepted
+    pub(crate) fn reading_options(&self) -> Self {
+        Self {
+            decompression: self.decompression,
+            read_limit: self.read_limit,
+            text: self.text,
+            ..Self::new()
+        }
+    }
+
+    fn has_valid_extension(&self, path: &Path) -> bool {
+        self.extensions.is_empty() || has_extension(path, &self.extensions)
+    }
+
+    /// Checks the rules for the file at `path` without opening it,
+    /// the file is then opened by [`open`](Self::open)
+    pub(crate) fn validate_path(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        path: &Path,
+    ) -> Result<(), clap::Error> {
+        let metadata = fs::metadata(path).map_err(|err| crate::open_error(cmd, arg, path, err))?;
+
+        self.validate(path, || Ok(metadata))
+            .map_err(|err| err.into_clap_error(cmd, arg, path))
+    }
+
+    /// Validates and opens the file at `path`
+    pub(crate) fn open_validated(&self, path: &Path) -> Result<NamedFile, FileError> {
+        // the rules are checked before opening the file, since opening a FIFO
+        // blocks until it has a writer
+        self.validate(path, || fs::metadata(path))?;
+
+        let file = self.options.open(path).map_err(FileError::Open)?;
+        self.named_file(file, path).map_err(FileError::Unusable)
+    }
+
+    /// Opens the file at `path`, without validating it
+    pub(crate) fn open(&self, path: &Path) -> io::Result<NamedFile> {
+        let file = self.options.open(path)?;
+        self.named_file(file, path)
+    }
+
+    /// Wraps an opened file, after checking that it isn't larger than the read limit
+    fn named_file(&self, file: fs::File, path: &Path) -> io::Result<NamedFile> {
+        let codec = self.decompression.codec(path, &file);
+
+        if let (Some(limit), None) = (self.read_limit, codec) {
+            // the decompressed size of compressed files is only known once they are read
+            if file
+                .metadata()
+                .is_ok_and(|metadata| metadata.is_file() && metadata.len() > limit)
+            {
+                return Err(crate::limit_error(limit));
+            }
+        }
+
+        Ok(NamedFile::new(file, path)
+            .with_codec(codec)
+            .with_limit(self.read_limit)
+            .with_encoding(self.text.encoding)
+            .with_lossy(self.text.lossy)
+            .with_normalized_newlines(self.text.normalize_newlines))
+    }
+
+    fn extension_reason(&self) -> String {
+        format!(
+            "expected a file with one of the extensions: {}",
+            self.extensions.join(", ")
+        )
+    }
+
+    /// Checks all rules for the file at `path`, without opening it
+    ///
+    /// `metadata` is only called if one of the rules depends on it
+    fn validate(
+        &self,
+        path: &Path,
+        metadata: impl FnOnce() -> io::Result<fs::Metadata>,
+    ) -> Result<(), FileError> {
+        if !self.has_valid_extension(path) {
+            return Err(FileError::Invalid(self.extension_reason()));
+        }
+
+        #[cfg(unix)]
+        let executable = self.executable;
+        #[cfg(not(unix))]
+        let executable = false;
+
+        let has_rules = self.kind.is_some()
+            || self.min_size.is_some()
+            || self.max_size.is_some()
+            || self.readable
+            || executable;
+
+        if !has_rules {
+            return Ok(());
+        }
+
+        // this also reports missing files, before the permissions are checked
+        let metadata = metadata().map_err(FileError::Open)?;
+
+        if let Some(kind) = self.kind {
+            check_kind(kind, metadata.file_type()).map_err(FileError::Invalid)?;
+        }
+
+        let size = metadata.len();
+
+        if let Some(min_size) = self.min_size {
+            if size < min_size {
+                return Err(FileError::Invalid(format!(
+                    "the file is {size} bytes large, but must be at least {min_size} bytes"
+                )));
+            }
+        }
+
+        if let Some(max_size) = self.max_size {
+            if size > max_size {
+                return Err(FileError::Invalid(format!(
+                    "the file is {size} bytes large, but must be at most {max_size} bytes"
+                )));
+            }
+        }
+
+        if self.readable && !is_accessible(path, Access::Read) {
+            return Err(FileError::Invalid("the file is not readable".into()));
+        }
+
+        #[cfg(unix)]
+        if self.executable && !is_accessible(path, Access::Execute) {
+            return Err(FileError::Invalid("the file is not executable".into()));
+        }
+
+        Ok(())
+    }
+}
+
+/// Why a [`NamedFileParser`] rejected a file, before it is reported as a [`clap::Error`]
+pub(crate) enum FileError {
+    /// The file could not be opened
+    Open(io::Error),
+    /// The file violates one of the configured rules
+    Invalid(String),
+    /// The file was opened, but can't be used, like when it exceeds the read limit
+    Unusable(io::Error),
+}
+
+impl FileError {
+    /// Reports the error for the argument `path`
+    pub(crate) fn into_clap_error(
+        self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        path: &Path,
+    ) -> clap::Error {
+        match self {
+            Self::Open(err) => crate::open_error(cmd, arg, path, err),
+            Self::Invalid(reason) => crate::validation_error(cmd, arg, path, reason),
+            Self::Unusable(err) => crate::value_error(cmd, arg, path.as_os_str(), err),
+        }
+    }
+}
+
+/// Normalizes an extension for [`has_extension`], `.JSON` becomes `json`
+pub(crate) fn normalize_extension(extension: impl Into<String>) -> String {
+    let extension: String = extension.into();
+    extension.trim_start_matches('.').to_ascii_lowercase()
+}
+
+/// Whether the file name of `path` ends with one of the normalized `extensions`
+pub(crate) fn has_extension(path: &Path, extensions: &[String]) -> bool {
+    let Some(file_name) = path.file_name() else {
+        return false;
+    };
+    let file_name = file_name.to_string_lossy().to_ascii_lowercase();
+
+    extensions.iter().any(|extension| {
+        file_name
+            .strip_suffix(extension.as_str())
+            .is_some_and(|stem| stem.len() > 1 && stem.ends_with('.'))
+    })
+}
+
+/// Checks that `file_type` is of the expected `kind`, returning why it isn't
+pub(crate) fn check_kind(kind: FileKind, file_type: fs::FileType) -> Result<(), String> {
+    match FileKind::of(file_type) {
+        Some(found) if found == kind => Ok(()),
+        Some(found) => Err(format!("expected {kind}, but found {found}")),
+        None => Err(format!("expected {kind}")),
+    }
+}
+
+pub(crate) enum Access {
+    Read,
+    Write,
+    #[cfg(unix)]
+    Execute,
+}
+
+#[cfg(unix)]
+pub(crate) fn is_accessible(path: &Path, access: Access) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let mode = match access {
+        Access::Read => libc::R_OK,
+        Access::Write => libc::W_OK,
+        Access::Execute => libc::X_OK,
+    };
+
+    // SAFETY: `path` is a valid nul-terminated string
+    unsafe { libc::access(path.as_ptr(), mode) == 0 }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn is_accessible(path: &Path, access: Access) -> bool {
+    match access {
+        Access::Read => fs::File::open(path).is_ok(),
+        Access::Write => {
+            fs::metadata(path).is_ok_and(|metadata| !metadata.permissions().readonly())
+        }
+    }
+}
+
+impl clap::builder::TypedValueParser for NamedFileParser {
+    type Value = NamedFile;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        self.parse(cmd, arg, value.into())
+    }
+
+    fn parse(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: std::ffi::OsString,
+    ) -> Result<Self::Value, clap::Error> {
+        let path = Path::new(&value);
+
+        self.open_validated(path)
+            .map_err(|err| err.into_clap_error(cmd, arg, path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Why `parser` rejects the file at `path`
+    fn rejection(parser: &NamedFileParser, path: &Path) -> String {
+        match parser.open_validated(path) {
+            Ok(_) => panic!("{} was accepted", path.display()),
+            Err(FileError::Invalid(reason)) => reason,
+            Err(FileError::Open(err) | FileError::Unusable(err)) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn kind() {
+        let dir = crate::test_dir("kind");
+        let file = dir.join("file.txt");
+        fs::write(&file, "").unwrap();
+
+        let files = NamedFileParser::new().kind(FileKind::File);
+        assert!(files.open_validated(&file).is_ok());
+        assert_eq!(
+            rejection(&files, &dir),
+            "expected a regular file, but found a directory"
+        );
+
+        let dirs = NamedFileParser::new().kind(FileKind::Dir);
+        assert!(dirs.open_validated(&dir).is_ok());
+        assert!(dirs.open_validated(&file).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn fifos_are_rejected_without_opening_them() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = crate::test_dir("fifo");
+        let fifo = dir.join("fifo");
+        let path = std::ffi::CString::new(fifo.as_os_str().as_bytes()).unwrap();
+        // SAFETY: `path` is a valid nul-terminated string
+        assert_eq!(unsafe { libc::mkfifo(path.as_ptr(), 0o644) }, 0);
+
+        // opening the FIFO would block forever, since it never gets a writer
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let path = fifo.clone();
+        std::thread::spawn(move || {
+            let parser = NamedFileParser::new().kind(FileKind::File);
+            sender.send(rejection(&parser, &path)).unwrap();
+        });
+
+        let reason = receiver
+            .recv_timeout(std::time::Duration::from_secs(5))
+            .expect("validating the FIFO blocked");
+        assert_eq!(reason, "expected a regular file, but found a FIFO");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn size() {
+        let dir = crate::test_dir("size");
+        let file = dir.join("file.txt");
+        fs::write(&file, "12345").unwrap();
+
+        let parser = NamedFileParser::new().min_size(5).max_size(5);
+        assert!(parser.open_validated(&file).is_ok());
+
+        assert_eq!(
+            rejection(&NamedFileParser::new().min_size(6), &file),
+            "the file is 5 bytes large, but must be at least 6 bytes"
+        );
+        assert_eq!(
+            rejection(&NamedFileParser::new().max_size(4), &file),
+            "the file is 5 bytes large, but must be at most 4 bytes"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_files_are_reported_before_other_rules() {
+        let dir = crate::test_dir("missing");
+        let parser = NamedFileParser::new().readable(true).min_size(1);
+
+        match parser.open_validated(&dir.join("missing.txt")) {
+            Err(FileError::Open(err)) => assert_eq!(err.kind(), io::ErrorKind::NotFound),
+            _ => panic!("the missing file wasn't reported as missing"),
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn readable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = crate::test_dir("readable");
+        let file = dir.join("file.txt");
+        fs::write(&file, "").unwrap();
+
+        let parser = NamedFileParser::new().readable(true);
+        assert!(parser.open_validated(&file).is_ok());
+
+        // root may read any file
+        // SAFETY: `geteuid` has no preconditions
+        if unsafe { libc::geteuid() } != 0 {
+            fs::set_permissions(&file, fs::Permissions::from_mode(0o200)).unwrap();
+            assert_eq!(rejection(&parser, &file), "the file is not readable");
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn executable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = crate::test_dir("executable");
+        let file = dir.join("script.sh");
+        fs::write(&file, "").unwrap();
+
+        let parser = NamedFileParser::new().executable(true);
+
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o644)).unwrap();
+        assert_eq!(rejection(&parser, &file), "the file is not executable");
+
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(parser.open_validated(&file).is_ok());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn extensions() {
+        let parser = NamedFileParser::new().extensions([".JSON", "tar.gz"]);
+
+        assert!(has_extension(Path::new("a.json"), &parser.extensions));
+        assert!(has_extension(Path::new("a.b.TAR.GZ"), &parser.extensions));
+        assert!(!has_extension(Path::new("a.gz"), &parser.extensions));
+        assert!(!has_extension(Path::new(".json"), &parser.extensions));
+        assert!(!has_extension(Path::new("ajson"), &parser.extensions));
+
+        assert_eq!(
+            rejection(&parser, Path::new("Cargo.toml")),
+            "expected a file with one of the extensions: json, tar.gz"
+        );
+    }
+}
@@ -0,0 +1,318 @@
+use std::{
+    ffi::{OsStr, OsString},
+    fs, io,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::EscapedPath,
+    parser::{check_kind, is_accessible, Access},
+    FileKind,
+};
+
+macro_rules! path_type {
+    (
+        $(#[$meta:meta])*
+        pub struct $name:ident;
+        parser = $parser:ident;
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+        pub struct $name {
+            path: PathBuf,
+        }
+
+        impl $name {
+            pub fn path(&self) -> &Path {
+                &self.path
+            }
+
+            pub fn into_path_buf(self) -> PathBuf {
+                self.path
+            }
+        }
+
+        impl Deref for $name {
+            type Target = Path;
+
+            #[inline]
+            fn deref(&self) -> &Self::Target {
+                &self.path
+            }
+        }
+
+        impl AsRef<Path> for $name {
+            #[inline]
+            fn as_ref(&self) -> &Path {
+                &self.path
+            }
+        }
+
+        impl AsRef<OsStr> for $name {
+            #[inline]
+            fn as_ref(&self) -> &OsStr {
+                self.path.as_os_str()
+            }
+        }
+
+        impl From<$name> for PathBuf {
+            #[inline]
+            fn from(path: $name) -> Self {
+                path.path
+            }
+        }
+
+        impl clap::builder::ValueParserFactory for $name {
+            type Parser = $parser;
+
+            #[inline]
+            fn value_parser() -> Self::Parser {
+                $parser::new()
+            }
+        }
+
+        impl clap::builder::TypedValueParser for $parser {
+            type Value = $name;
+
+            fn parse_ref(
+                &self,
+                cmd: &clap::Command,
+                arg: Option<&clap::Arg>,
+                value: &OsStr,
+            ) -> Result<Self::Value, clap::Error> {
+                self.parse(cmd, arg, value.into())
+            }
+
+            fn parse(
+                &self,
+                cmd: &clap::Command,
+                arg: Option<&clap::Arg>,
+                value: OsString,
+            ) -> Result<Self::Value, clap::Error> {
+                self.validate(cmd, arg, Path::new(&value))?;
+                Ok($name { path: value.into() })
+            }
+        }
+    };
+}
+
+path_type! {
+    /// A path which exists, without opening it
+    ///
+    /// This is useful for paths which are passed on to other programs. Errors
+    /// are reported just like [`NamedFileParser`](crate::NamedFileParser) reports them.
+    ///
+    /// ```rust
+    /// # use clap::Parser;
+    /// # use clap_file::{ExistingPath, ExistingPathParser, FileKind};
+    /// #[derive(clap::Parser)]
+    /// struct CliArgs {
+    ///     #[arg(long)]
+    ///     any: Option<ExistingPath>,
+    ///     #[arg(long, value_parser = ExistingPathParser::new().kind(FileKind::File))]
+    ///     file: Option<ExistingPath>,
+    /// }
+    ///
+    /// assert!(CliArgs::try_parse_from(["prog", "--any", "src"]).is_ok());
+    /// assert!(CliArgs::try_parse_from(["prog", "--any", "missing.txt"]).is_err());
+    /// assert!(CliArgs::try_parse_from(["prog", "--file", "src"]).is_err());
+    /// ```
+    pub struct ExistingPath;
+    parser = ExistingPathParser;
+}
+
+path_type! {
+    /// A path to a directory which exists, without opening it
+    ///
+    /// ```rust
+    /// # use clap::Parser;
+    /// # use clap_file::ExistingDir;
+    /// #[derive(clap::Parser)]
+    /// struct CliArgs {
+    ///     out_dir: ExistingDir,
+    /// }
+    ///
+    /// assert!(CliArgs::try_parse_from(["prog", "src"]).is_ok());
+    /// assert!(CliArgs::try_parse_from(["prog", "Cargo.toml"]).is_err());
+    /// ```
+    pub struct ExistingDir;
+    parser = ExistingDirParser;
+}
+
+path_type! {
+    /// A path which doesn't exist yet, in a writable directory
+    ///
+    /// This is useful for paths which will be created later, possibly by
+    /// another program. The path must end in a file name, so paths like
+    /// `foo/..` are rejected.
+    ///
+    /// ```rust
+    /// # use clap::Parser;
+    /// # use clap_file::NonExistingPath;
+    /// #[derive(clap::Parser)]
+    /// struct CliArgs {
+    ///     output: NonExistingPath,
+    /// }
+    ///
+    /// assert!(CliArgs::try_parse_from(["prog", "target/new-output.txt"]).is_ok());
+    /// assert!(CliArgs::try_parse_from(["prog", "Cargo.toml"]).is_err());
+    /// assert!(CliArgs::try_parse_from(["prog", "missing/output.txt"]).is_err());
+    /// assert!(CliArgs::try_parse_from(["prog", ""]).is_err());
+    /// assert!(CliArgs::try_parse_from(["prog", "target/missing/.."]).is_err());
+    /// ```
+    pub struct NonExistingPath;
+    parser = NonExistingPathParser;
+}
+
+/// A clap parser for parsing [`ExistingPaths`](ExistingPath)
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ExistingPathParser {
+    kind: Option<FileKind>,
+}
+
+/// A clap parser for parsing [`ExistingDirs`](ExistingDir)
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ExistingDirParser {
+    writable: bool,
+}
+
+/// A clap parser for parsing [`NonExistingPaths`](NonExistingPath)
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NonExistingPathParser {
+    _private: (),
+}
+
+impl ExistingPathParser {
+    #[inline]
+    pub const fn new() -> Self {
+        Self { kind: None }
+    }
+
+    /// Only accept paths of the given kind
+    #[inline]
+    pub const fn kind(mut self, kind: FileKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    fn validate(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        path: &Path,
+    ) -> Result<(), clap::Error> {
+        let metadata = fs::metadata(path).map_err(|err| crate::open_error(cmd, arg, path, err))?;
+
+        if let Some(kind) = self.kind {
+            check_kind(kind, metadata.file_type())
+                .map_err(|reason| crate::validation_error(cmd, arg, path, reason))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ExistingDirParser {
+    #[inline]
+    pub const fn new() -> Self {
+        Self { writable: false }
+    }
+
+    /// Only accept directories which the current user may create files in
+    #[inline]
+    pub const fn writable(mut self, writable: bool) -> Self {
+        self.writable = writable;
+        self
+    }
+
+    fn validate(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        path: &Path,
+    ) -> Result<(), clap::Error> {
+        ExistingPathParser::new()
+            .kind(FileKind::Dir)
+            .validate(cmd, arg, path)?;
+
+        if self.writable && !is_accessible(path, Access::Write) {
+            return Err(crate::validation_error(
+                cmd,
+                arg,
+                path,
+                "the directory is not writable",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl NonExistingPathParser {
+    #[inline]
+    pub const fn new() -> Self {
+        Self { _private: () }
+    }
+
+    fn validate(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        path: &Path,
+    ) -> Result<(), clap::Error> {
+        // paths like `foo/..` or `/` don't name a file which could be created
+        if path.as_os_str().is_empty() {
+            return Err(crate::validation_error(cmd, arg, path, "the path is empty"));
+        } else if path.file_name().is_none() {
+            return Err(crate::validation_error(
+                cmd,
+                arg,
+                path,
+                "the path does not end in a file name",
+            ));
+        }
+
+        match fs::symlink_metadata(path) {
+            Ok(_) => {
+                return Err(crate::value_error(
+                    cmd,
+                    arg,
+                    path.as_os_str(),
+                    io::Error::new(io::ErrorKind::AlreadyExists, "the path already exists"),
+                ))
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+            Err(err) => return Err(crate::open_error(cmd, arg, path, err)),
+        }
+
+        let parent = crate::parent_dir(path);
+
+        // reported for the path itself, so the hints point out the missing directory
+        let metadata =
+            fs::metadata(parent).map_err(|err| crate::open_error(cmd, arg, path, err))?;
+
+        if !metadata.is_dir() {
+            return Err(crate::open_error(
+                cmd,
+                arg,
+                path,
+                io::ErrorKind::NotADirectory.into(),
+            ));
+        }
+
+        if !is_accessible(parent, Access::Write) {
+            return Err(crate::value_error(
+                cmd,
+                arg,
+                path.as_os_str(),
+                io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!("the directory {} is not writable", EscapedPath(parent)),
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+}
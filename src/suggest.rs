@@ -0,0 +1,157 @@
+use std::{fs, io, path::Path};
+
+use crate::error::EscapedPath;
+
+/// How similar a file name has to be to be suggested
+const SIMILARITY_THRESHOLD: f64 = 0.8;
+
+/// The maximum number of similar files which are suggested
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Hints which explain why a file could not be opened
+#[derive(Default)]
+pub(crate) struct Hints {
+    /// Existing paths which are similar to the one that could not be found
+    pub similar: Vec<String>,
+    /// Free form tips
+    pub tips: Vec<String>,
+}
+
+impl Hints {
+    pub(crate) fn for_open_error(path: &Path, err: &io::Error) -> Self {
+        let mut hints = Self::default();
+
+        if is_out_of_file_descriptors(err) {
+            hints.tips.push(
+                "the process ran out of file descriptors, try raising the limit with 'ulimit -n'"
+                    .into(),
+            );
+            return hints;
+        }
+
+        match err.kind() {
+            io::ErrorKind::NotFound => match missing_dir(path) {
+                Some(dir) => hints.tips.push(format!(
+                    "the directory '{}' does not exist",
+                    EscapedPath(dir)
+                )),
+                None => hints.similar = similar_files(path),
+            },
+            io::ErrorKind::PermissionDenied => hints.tips.push(permission_tip(path)),
+            io::ErrorKind::IsADirectory => hints
+                .tips
+                .push("the path is a directory, but a file was expected".into()),
+            io::ErrorKind::NotADirectory => hints
+                .tips
+                .push("a component of the path is not a directory".into()),
+            _ => (),
+        }
+
+        hints
+    }
+}
+
+#[cfg(unix)]
+fn is_out_of_file_descriptors(err: &io::Error) -> bool {
+    matches!(err.raw_os_error(), Some(libc::EMFILE | libc::ENFILE))
+}
+
+#[cfg(not(unix))]
+fn is_out_of_file_descriptors(_err: &io::Error) -> bool {
+    false
+}
+
+/// The closest ancestor of `path` which does not exist, if any ancestor is missing
+fn missing_dir(path: &Path) -> Option<&Path> {
+    let mut missing = None;
+    let mut dir = path.parent();
+
+    while let Some(current) = dir.filter(|dir| !dir.as_os_str().is_empty()) {
+        if current.exists() {
+            break;
+        }
+
+        missing = Some(current);
+        dir = current.parent();
+    }
+
+    missing
+}
+
+/// Paths next to `path` whose file names are similar to the file name of `path`
+fn similar_files(path: &Path) -> Vec<String> {
+    let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(crate::parent_dir(path)) else {
+        return Vec::new();
+    };
+
+    let mut candidates = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .map(|candidate| (strsim::jaro(file_name, &candidate), candidate))
+        .filter(|(confidence, _)| *confidence > SIMILARITY_THRESHOLD)
+        .collect::<Vec<_>>();
+
+    candidates.sort_by(|(a, a_name), (b, b_name)| b.total_cmp(a).then_with(|| a_name.cmp(b_name)));
+
+    candidates
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| {
+            let mut similar = path.to_path_buf();
+            similar.set_file_name(candidate);
+            EscapedPath(&similar).to_string()
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+fn permission_tip(path: &Path) -> String {
+    use std::os::unix::fs::MetadataExt;
+
+    match fs::metadata(path) {
+        Ok(metadata) => format!(
+            "the file is owned by {} and has mode {:o}",
+            user_name(metadata.uid()).unwrap_or_else(|| format!("uid {}", metadata.uid())),
+            metadata.mode() & 0o7777,
+        ),
+        Err(_) => format!(
+            "the directory '{}' can't be accessed",
+            EscapedPath(crate::parent_dir(path))
+        ),
+    }
+}
+
+#[cfg(not(unix))]
+fn permission_tip(_path: &Path) -> String {
+    "the file can't be accessed by the current user".into()
+}
+
+#[cfg(unix)]
+fn user_name(uid: u32) -> Option<String> {
+    let mut buffer = vec![0; 4096];
+    // SAFETY: `passwd` is a plain C struct, for which all zeros is a valid value
+    let mut passwd = unsafe { std::mem::zeroed::<libc::passwd>() };
+    let mut result = std::ptr::null_mut();
+
+    // SAFETY: all pointers are valid, and `buffer.len()` is the length of `buffer`
+    let status = unsafe {
+        libc::getpwuid_r(
+            uid,
+            &mut passwd,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut result,
+        )
+    };
+
+    if status != 0 || result.is_null() {
+        return None;
+    }
+
+    // SAFETY: on success `pw_name` points to a nul-terminated string inside of `buffer`
+    let name = unsafe { std::ffi::CStr::from_ptr(passwd.pw_name) };
+    name.to_str().ok().map(String::from)
+}
@@ -0,0 +1,269 @@
+use std::io;
+
+/// The text encoding [`NamedFile::read_to_string`](crate::NamedFile::read_to_string)
+/// decodes files with
+///
+/// A byte order mark at the start of a file always takes precedence over the
+/// configured encoding, and is removed from the decoded text.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// Any other encoding supported by [`encoding_rs`], like `windows-1252`
+    #[cfg(feature = "encoding")]
+    Other(&'static encoding_rs::Encoding),
+}
+
+impl Encoding {
+    /// Looks up an encoding by one of its [WHATWG labels](https://encoding.spec.whatwg.org/#names-and-labels),
+    /// like `utf-16le` or `latin1`
+    ///
+    /// ```rust
+    /// # use clap_file::Encoding;
+    /// assert_eq!(Encoding::for_label("UTF-16LE"), Some(Encoding::Utf16Le));
+    /// assert_eq!(Encoding::for_label("latin1").unwrap().name(), "windows-1252");
+    /// assert_eq!(Encoding::for_label("klingon"), None);
+    /// ```
+    #[cfg(feature = "encoding")]
+    pub fn for_label(label: &str) -> Option<Self> {
+        encoding_rs::Encoding::for_label(label.as_bytes()).map(Self::from)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Utf8 => "UTF-8",
+            Self::Utf16Le => "UTF-16LE",
+            Self::Utf16Be => "UTF-16BE",
+            #[cfg(feature = "encoding")]
+            Self::Other(encoding) => encoding.name(),
+        }
+    }
+
+    /// The encoding announced by the byte order mark at the start of `bytes`,
+    /// and the length of the byte order mark
+    fn from_bom(bytes: &[u8]) -> Option<(Self, usize)> {
+        match bytes {
+            [0xEF, 0xBB, 0xBF, ..] => Some((Self::Utf8, 3)),
+            [0xFF, 0xFE, ..] => Some((Self::Utf16Le, 2)),
+            [0xFE, 0xFF, ..] => Some((Self::Utf16Be, 2)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "encoding")]
+impl From<&'static encoding_rs::Encoding> for Encoding {
+    fn from(encoding: &'static encoding_rs::Encoding) -> Self {
+        if encoding == encoding_rs::UTF_8 {
+            Self::Utf8
+        } else if encoding == encoding_rs::UTF_16LE {
+            Self::Utf16Le
+        } else if encoding == encoding_rs::UTF_16BE {
+            Self::Utf16Be
+        } else {
+            Self::Other(encoding)
+        }
+    }
+}
+
+/// How the bytes of a file are turned into text
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct TextOptions {
+    /// The encoding of files without a byte order mark, UTF-8 if unset
+    pub encoding: Option<Encoding>,
+    /// Replace invalid sequences with U+FFFD instead of failing
+    pub lossy: bool,
+    /// Replace `\r\n` with `\n`
+    pub normalize_newlines: bool,
+}
+
+impl TextOptions {
+    pub fn decode(self, mut bytes: Vec<u8>) -> io::Result<String> {
+        let encoding = match Encoding::from_bom(&bytes) {
+            Some((encoding, bom_len)) => {
+                bytes.drain(..bom_len);
+                encoding
+            }
+            None => self.encoding.unwrap_or(Encoding::Utf8),
+        };
+
+        let text = match encoding {
+            Encoding::Utf8 => match String::from_utf8(bytes) {
+                Ok(text) => text,
+                Err(err) if self.lossy => String::from_utf8_lossy(err.as_bytes()).into_owned(),
+                Err(err) => return Err(invalid_data(err.utf8_error())),
+            },
+            Encoding::Utf16Le => decode_utf16(&bytes, u16::from_le_bytes, self.lossy)?,
+            Encoding::Utf16Be => decode_utf16(&bytes, u16::from_be_bytes, self.lossy)?,
+            #[cfg(feature = "encoding")]
+            Encoding::Other(encoding) if self.lossy => {
+                encoding.decode_without_bom_handling(&bytes).0.into_owned()
+            }
+            #[cfg(feature = "encoding")]
+            Encoding::Other(encoding) => encoding
+                .decode_without_bom_handling_and_without_replacement(&bytes)
+                .ok_or_else(|| invalid_data(format!("the text is not valid {}", encoding.name())))?
+                .into_owned(),
+        };
+
+        if self.normalize_newlines && text.contains("\r\n") {
+            Ok(text.replace("\r\n", "\n"))
+        } else {
+            Ok(text)
+        }
+    }
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16, lossy: bool) -> io::Result<String> {
+    let chunks = bytes.chunks_exact(2);
+    let odd_length = !chunks.remainder().is_empty();
+
+    if odd_length && !lossy {
+        return Err(invalid_data("the UTF-16 text has an odd number of bytes"));
+    }
+
+    let units = chunks.map(|chunk| from_bytes([chunk[0], chunk[1]]));
+    let mut text = String::with_capacity(bytes.len() / 2);
+
+    for c in char::decode_utf16(units) {
+        match c {
+            Ok(c) => text.push(c),
+            Err(_) if lossy => text.push(char::REPLACEMENT_CHARACTER),
+            Err(err) => {
+                return Err(invalid_data(format!(
+                    "invalid UTF-16: unpaired surrogate {:#06x}",
+                    err.unpaired_surrogate()
+                )))
+            }
+        }
+    }
+
+    if odd_length {
+        text.push(char::REPLACEMENT_CHARACTER);
+    }
+
+    Ok(text)
+}
+
+fn invalid_data(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(encoding: Encoding, lossy: bool) -> TextOptions {
+        TextOptions {
+            encoding: Some(encoding),
+            lossy,
+            normalize_newlines: false,
+        }
+    }
+
+    fn utf16le(units: &[u16]) -> Vec<u8> {
+        units.iter().flat_map(|unit| unit.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn odd_length_utf16() {
+        let bytes = b"a\0b".to_vec();
+
+        let err = options(Encoding::Utf16Le, false)
+            .decode(bytes.clone())
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let text = options(Encoding::Utf16Le, true).decode(bytes).unwrap();
+        assert_eq!(text, "a\u{FFFD}");
+    }
+
+    #[test]
+    fn unpaired_surrogates_in_strict_mode() {
+        let bytes = utf16le(&[0x61, 0xD800, 0x62]);
+
+        let err = options(Encoding::Utf16Le, false).decode(bytes).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "invalid UTF-16: unpaired surrogate 0xd800");
+    }
+
+    #[test]
+    fn unpaired_surrogates_in_lossy_mode() {
+        let bytes = utf16le(&[0xDC00, 0x61, 0xD800]);
+        let text = options(Encoding::Utf16Le, true).decode(bytes).unwrap();
+        assert_eq!(text, "\u{FFFD}a\u{FFFD}");
+    }
+
+    #[test]
+    fn surrogate_pairs() {
+        let bytes: Vec<u8> = [0xD83D_u16, 0xDE00]
+            .iter()
+            .flat_map(|unit| unit.to_be_bytes())
+            .collect();
+        let text = options(Encoding::Utf16Be, false).decode(bytes).unwrap();
+        assert_eq!(text, "\u{1F600}");
+    }
+
+    #[test]
+    fn byte_order_marks_are_stripped() {
+        let text = TextOptions::default()
+            .decode(b"\xEF\xBB\xBFa".to_vec())
+            .unwrap();
+        assert_eq!(text, "a");
+
+        let text = TextOptions::default()
+            .decode(b"\xFF\xFEa\0".to_vec())
+            .unwrap();
+        assert_eq!(text, "a");
+
+        let text = TextOptions::default()
+            .decode(b"\xFE\xFF\0a".to_vec())
+            .unwrap();
+        assert_eq!(text, "a");
+    }
+
+    #[test]
+    fn byte_order_marks_take_precedence() {
+        let text = options(Encoding::Utf16Be, false)
+            .decode(b"\xFF\xFEa\0".to_vec())
+            .unwrap();
+        assert_eq!(text, "a");
+
+        let text = options(Encoding::Utf16Le, false)
+            .decode(b"\xEF\xBB\xBFa".to_vec())
+            .unwrap();
+        assert_eq!(text, "a");
+    }
+
+    #[test]
+    fn only_the_first_byte_order_mark_is_stripped() {
+        let text = TextOptions::default()
+            .decode("\u{FEFF}\u{FEFF}a".into())
+            .unwrap();
+        assert_eq!(text, "\u{FEFF}a");
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let err = TextOptions::default()
+            .decode(b"a\xFFb".to_vec())
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let text = options(Encoding::Utf8, true)
+            .decode(b"a\xFFb".to_vec())
+            .unwrap();
+        assert_eq!(text, "a\u{FFFD}b");
+    }
+
+    #[test]
+    fn newlines_are_normalized_after_decoding() {
+        let mut options = options(Encoding::Utf16Le, false);
+        options.normalize_newlines = true;
+
+        let text = options.decode(utf16le(&[0x61, 0x0D, 0x0A, 0x0D])).unwrap();
+        assert_eq!(text, "a\n\r");
+    }
+}
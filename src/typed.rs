@@ -0,0 +1,662 @@
+use std::{
+    fmt,
+    marker::PhantomData,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+#[cfg(any(feature = "json", feature = "yaml"))]
+use serde::de::IgnoredAny;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    error::EscapedPath, IoError, NamedFile, NamedFileParser, NamedOutputFile,
+    NamedOutputFileParser, Operation,
+};
+
+/// The formats which files can be deserialized from
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Format {
+    #[cfg(feature = "json")]
+    Json,
+    #[cfg(feature = "toml")]
+    Toml,
+    #[cfg(feature = "yaml")]
+    Yaml,
+}
+
+impl Format {
+    /// Picks the format from the extension of `path`
+    ///
+    /// The extension of a compressed file's codec is skipped, so `data.json.gz` is JSON
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let path = match crate::Codec::from_path(path) {
+            Some(_) => Path::new(path.file_stem()?),
+            None => path,
+        };
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+        match extension.as_str() {
+            #[cfg(feature = "json")]
+            "json" => Some(Self::Json),
+            #[cfg(feature = "toml")]
+            "toml" => Some(Self::Toml),
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+
+    /// The canonical extension of files in this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            #[cfg(feature = "json")]
+            Self::Json => "json",
+            #[cfg(feature = "toml")]
+            Self::Toml => "toml",
+            #[cfg(feature = "yaml")]
+            Self::Yaml => "yaml",
+        }
+    }
+
+    /// All formats whose features are enabled
+    const ENABLED: &'static [Self] = &[
+        #[cfg(feature = "json")]
+        Self::Json,
+        #[cfg(feature = "toml")]
+        Self::Toml,
+        #[cfg(feature = "yaml")]
+        Self::Yaml,
+    ];
+
+    /// Guesses the format from the contents of a file, by picking the first format which can parse it
+    pub fn detect(text: &str) -> Self {
+        Self::ENABLED
+            .iter()
+            .copied()
+            .find(|format| format.can_parse(text))
+            .unwrap_or(Self::ENABLED[0])
+    }
+
+    fn can_parse(self, text: &str) -> bool {
+        match self {
+            #[cfg(feature = "json")]
+            Self::Json => serde_json::from_str::<IgnoredAny>(text).is_ok(),
+            #[cfg(feature = "toml")]
+            Self::Toml => text.parse::<toml::Table>().is_ok(),
+            #[cfg(feature = "yaml")]
+            Self::Yaml => serde_yaml::from_str::<IgnoredAny>(text).is_ok(),
+        }
+    }
+
+    fn serialize<T: Serialize + ?Sized>(self, value: &T, pretty: bool) -> Result<String, String> {
+        match (self, pretty) {
+            #[cfg(feature = "json")]
+            (Self::Json, true) => {
+                serde_json::to_string_pretty(value).map_err(|err| err.to_string())
+            }
+            #[cfg(feature = "json")]
+            (Self::Json, false) => serde_json::to_string(value).map_err(|err| err.to_string()),
+            #[cfg(feature = "toml")]
+            (Self::Toml, true) => toml::to_string_pretty(value).map_err(|err| err.to_string()),
+            #[cfg(feature = "toml")]
+            (Self::Toml, false) => toml::to_string(value).map_err(|err| err.to_string()),
+            // YAML has no compact form
+            #[cfg(feature = "yaml")]
+            (Self::Yaml, _) => serde_yaml::to_string(value).map_err(|err| err.to_string()),
+        }
+    }
+
+    fn deserialize<T: DeserializeOwned>(
+        self,
+        path: &Path,
+        text: &str,
+    ) -> Result<T, DeserializeError> {
+        let error = |message: String, location: Option<(usize, usize)>| DeserializeError {
+            path: path.into(),
+            format: self,
+            message,
+            location,
+        };
+
+        match self {
+            #[cfg(feature = "json")]
+            Self::Json => serde_json::from_str(text).map_err(|err| {
+                let location = (err.line() != 0).then(|| (err.line(), err.column()));
+                error(strip_location(err.to_string(), location), location)
+            }),
+            #[cfg(feature = "toml")]
+            Self::Toml => toml::from_str(text).map_err(|err| {
+                let location = err.span().map(|span| line_column(text, span.start));
+                error(err.message().to_owned(), location)
+            }),
+            #[cfg(feature = "yaml")]
+            Self::Yaml => serde_yaml::from_str(text).map_err(|err| {
+                let location = err
+                    .location()
+                    .map(|location| (location.line(), location.column()));
+                error(strip_location(err.to_string(), location), location)
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match *self {
+            #[cfg(feature = "json")]
+            Self::Json => "JSON",
+            #[cfg(feature = "toml")]
+            Self::Toml => "TOML",
+            #[cfg(feature = "yaml")]
+            Self::Yaml => "YAML",
+        })
+    }
+}
+
+/// Removes the ` at line N column M` suffix which serde_json and serde_yaml add to their messages,
+/// since the location is reported separately
+#[cfg(any(feature = "json", feature = "yaml"))]
+fn strip_location(mut message: String, location: Option<(usize, usize)>) -> String {
+    if let Some((line, column)) = location {
+        let suffix = format!(" at line {line} column {column}");
+        if message.ends_with(&suffix) {
+            message.truncate(message.len() - suffix.len());
+        }
+    }
+
+    message
+}
+
+/// The 1-based line and column of the byte at `offset`
+#[cfg(feature = "toml")]
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |index| index + 1) + 1;
+    (line, column)
+}
+
+/// The error reported when a file could not be deserialized
+#[derive(Debug)]
+pub struct DeserializeError {
+    path: PathBuf,
+    format: Format,
+    message: String,
+    location: Option<(usize, usize)>,
+}
+
+impl DeserializeError {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// The 1-based line the error occurred on, if known
+    pub fn line(&self) -> Option<usize> {
+        self.location.map(|(line, _)| line)
+    }
+
+    /// The 1-based column the error occurred on, if known
+    pub fn column(&self) -> Option<usize> {
+        self.location.map(|(_, column)| column)
+    }
+}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "could not parse {} as {}",
+            EscapedPath(&self.path),
+            self.format
+        )?;
+
+        if let Some((line, column)) = self.location {
+            write!(f, " at line {line}, column {column}")?;
+        }
+
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl std::error::Error for DeserializeError {}
+
+/// Opens a file, and deserializes it in the given format or the format detected from the file
+fn parse_file<T: DeserializeOwned>(
+    files: &NamedFileParser,
+    format: Option<Format>,
+    cmd: &clap::Command,
+    arg: Option<&clap::Arg>,
+    value: std::ffi::OsString,
+) -> Result<(T, PathBuf), clap::Error> {
+    use clap::builder::TypedValueParser;
+
+    let file: NamedFile = files.parse_ref(cmd, arg, &value)?;
+    let text = file
+        .read_to_string()
+        .map_err(|err| crate::value_error(cmd, arg, &value, err))?;
+
+    let format = format
+        .or_else(|| Format::from_path(file.path()))
+        .unwrap_or_else(|| Format::detect(&text));
+    let value = format
+        .deserialize(file.path(), &text)
+        .map_err(|err| crate::value_error(cmd, arg, &value, err))?;
+
+    Ok((value, file.path().into()))
+}
+
+macro_rules! typed_file {
+    (
+        #[cfg($cfg:meta)]
+        $(#[$meta:meta])*
+        $name:ident, $parser:ident, $format:expr
+    ) => {
+        $(#[$meta])*
+        #[cfg($cfg)]
+        #[derive(Clone, Debug)]
+        pub struct $name<T> {
+            value: T,
+            path: PathBuf,
+        }
+
+        #[doc = concat!("A clap parser for parsing [`", stringify!($name), "s`](", stringify!($name), ")")]
+        ///
+        /// Files are opened with the wrapped [`NamedFileParser`]
+        #[cfg($cfg)]
+        pub struct $parser<T> {
+            files: NamedFileParser,
+            _marker: PhantomData<fn() -> T>,
+        }
+
+        #[cfg($cfg)]
+        impl<T> $name<T> {
+            pub fn value(&self) -> &T {
+                &self.value
+            }
+
+            pub fn into_value(self) -> T {
+                self.value
+            }
+
+            pub fn path(&self) -> &Path {
+                &self.path
+            }
+        }
+
+        #[cfg($cfg)]
+        impl<T> Deref for $name<T> {
+            type Target = T;
+
+            #[inline]
+            fn deref(&self) -> &T {
+                &self.value
+            }
+        }
+
+        #[cfg($cfg)]
+        impl<T> $parser<T> {
+            #[inline]
+            pub fn new() -> Self {
+                NamedFileParser::new().into()
+            }
+        }
+
+        #[cfg($cfg)]
+        impl<T> Default for $parser<T> {
+            #[inline]
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        #[cfg($cfg)]
+        impl<T> From<NamedFileParser> for $parser<T> {
+            #[inline]
+            fn from(files: NamedFileParser) -> Self {
+                Self {
+                    files,
+                    _marker: PhantomData,
+                }
+            }
+        }
+
+        #[cfg($cfg)]
+        impl<T> Clone for $parser<T> {
+            #[inline]
+            fn clone(&self) -> Self {
+                self.files.clone().into()
+            }
+        }
+
+        #[cfg($cfg)]
+        impl<T> fmt::Debug for $parser<T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct(stringify!($parser))
+                    .field("files", &self.files)
+                    .finish()
+            }
+        }
+
+        #[cfg($cfg)]
+        impl<T> clap::builder::ValueParserFactory for $name<T>
+        where
+            T: DeserializeOwned + Clone + Send + Sync + 'static,
+        {
+            type Parser = $parser<T>;
+
+            #[inline]
+            fn value_parser() -> Self::Parser {
+                $parser::new()
+            }
+        }
+
+        #[cfg($cfg)]
+        impl<T> clap::builder::TypedValueParser for $parser<T>
+        where
+            T: DeserializeOwned + Clone + Send + Sync + 'static,
+        {
+            type Value = $name<T>;
+
+            fn parse_ref(
+                &self,
+                cmd: &clap::Command,
+                arg: Option<&clap::Arg>,
+                value: &std::ffi::OsStr,
+            ) -> Result<Self::Value, clap::Error> {
+                self.parse(cmd, arg, value.into())
+            }
+
+            fn parse(
+                &self,
+                cmd: &clap::Command,
+                arg: Option<&clap::Arg>,
+                value: std::ffi::OsString,
+            ) -> Result<Self::Value, clap::Error> {
+                let (value, path) = parse_file(&self.files, $format, cmd, arg, value)?;
+                Ok($name { value, path })
+            }
+        }
+    };
+}
+
+typed_file! {
+    #[cfg(feature = "json")]
+    /// A file which is deserialized from JSON while parsing the arguments
+    ///
+    /// ```rust
+    /// # use clap_file::JsonFile;
+    /// #[derive(Clone, serde::Deserialize)]
+    /// struct Settings {
+    ///     name: String,
+    /// }
+    ///
+    /// #[derive(clap::Parser)]
+    /// struct CliArgs {
+    ///     settings: JsonFile<Settings>,
+    /// }
+    /// ```
+    JsonFile, JsonFileParser, Some(Format::Json)
+}
+
+typed_file! {
+    #[cfg(feature = "toml")]
+    /// A file which is deserialized from TOML while parsing the arguments
+    ///
+    /// ```rust
+    /// # use clap::Parser;
+    /// # use clap_file::TomlFile;
+    /// #[derive(Clone, serde::Deserialize)]
+    /// struct Manifest {
+    ///     package: Package,
+    /// }
+    ///
+    /// #[derive(Clone, serde::Deserialize)]
+    /// struct Package {
+    ///     name: String,
+    /// }
+    ///
+    /// #[derive(clap::Parser)]
+    /// struct CliArgs {
+    ///     manifest: TomlFile<Manifest>,
+    /// }
+    ///
+    /// let args = CliArgs::try_parse_from(["prog", "Cargo.toml"]).unwrap();
+    /// assert_eq!(args.manifest.package.name, "clap-file");
+    /// ```
+    TomlFile, TomlFileParser, Some(Format::Toml)
+}
+
+typed_file! {
+    #[cfg(feature = "yaml")]
+    /// A file which is deserialized from YAML while parsing the arguments
+    ///
+    /// ```rust
+    /// # use clap_file::YamlFile;
+    /// #[derive(Clone, serde::Deserialize)]
+    /// struct Pipeline {
+    ///     steps: Vec<String>,
+    /// }
+    ///
+    /// #[derive(clap::Parser)]
+    /// struct CliArgs {
+    ///     pipeline: YamlFile<Pipeline>,
+    /// }
+    /// ```
+    YamlFile, YamlFileParser, Some(Format::Yaml)
+}
+
+typed_file! {
+    #[cfg(any(feature = "json", feature = "toml", feature = "yaml"))]
+    /// A file which is deserialized while parsing the arguments, in the format
+    /// given by its extension, or detected from its contents
+    ///
+    /// Only the formats whose features are enabled are supported
+    ///
+    /// ```rust
+    /// # use clap_file::ConfigFile;
+    /// #[derive(Clone, serde::Deserialize)]
+    /// struct Settings {
+    ///     name: String,
+    /// }
+    ///
+    /// #[derive(clap::Parser)]
+    /// struct CliArgs {
+    ///     #[arg(long)]
+    ///     config: ConfigFile<Settings>,
+    /// }
+    /// ```
+    ConfigFile, ConfigFileParser, None
+}
+
+/// An output which values can be serialized to, in the format given by its
+/// extension or configured on the [`TypedOutputFileParser`]
+///
+/// Like [`NamedOutputFile`], `-` is interpreted as stdout. Stdout uses the
+/// configured format, or JSON if it's enabled and no format was configured.
+///
+/// ```rust
+/// # use clap::Parser;
+/// # use clap_file::{TypedOutputFile, TypedOutputFileParser};
+/// #[derive(serde::Serialize)]
+/// struct Report {
+///     errors: usize,
+/// }
+///
+/// #[derive(clap::Parser)]
+/// struct CliArgs {
+///     #[arg(short, long, value_parser = TypedOutputFileParser::new().pretty(false))]
+///     output: TypedOutputFile,
+/// }
+///
+/// # #[cfg(feature = "json")] {
+/// let target = std::env::temp_dir().join("clap-file-typed-output-example.json");
+/// let args = CliArgs::try_parse_from(["prog".as_ref(), "-o".as_ref(), target.as_os_str()]).unwrap();
+/// args.output.write(&Report { errors: 0 }).unwrap();
+///
+/// assert_eq!(std::fs::read_to_string(&target).unwrap(), "{\"errors\":0}\n");
+/// # std::fs::remove_file(&target).unwrap();
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct TypedOutputFile {
+    output: NamedOutputFile,
+    format: Format,
+    pretty: bool,
+    trailing_newline: bool,
+}
+
+/// A clap parser for parsing [`TypedOutputFiles`](TypedOutputFile)
+///
+/// Files are opened with the wrapped [`NamedOutputFileParser`]
+#[derive(Clone, Debug)]
+pub struct TypedOutputFileParser {
+    output: NamedOutputFileParser,
+    format: Option<Format>,
+    pretty: bool,
+    trailing_newline: bool,
+}
+
+impl clap::builder::ValueParserFactory for TypedOutputFile {
+    type Parser = TypedOutputFileParser;
+
+    #[inline]
+    fn value_parser() -> Self::Parser {
+        TypedOutputFileParser::new()
+    }
+}
+
+impl Default for TypedOutputFileParser {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<NamedOutputFileParser> for TypedOutputFileParser {
+    #[inline]
+    fn from(output: NamedOutputFileParser) -> Self {
+        Self {
+            output,
+            format: None,
+            pretty: true,
+            trailing_newline: true,
+        }
+    }
+}
+
+impl TypedOutputFileParser {
+    /// Creates a parser which writes pretty output followed by a newline
+    #[inline]
+    pub fn new() -> Self {
+        NamedOutputFileParser::new().into()
+    }
+
+    /// Always use the given format, instead of inferring it from the extension
+    #[inline]
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Whether to write pretty or compact output
+    #[inline]
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty;
+        self
+    }
+
+    /// Whether to make sure the output ends with a newline
+    #[inline]
+    pub fn trailing_newline(mut self, trailing_newline: bool) -> Self {
+        self.trailing_newline = trailing_newline;
+        self
+    }
+}
+
+impl TypedOutputFile {
+    /// Serializes `value` and writes it to the output
+    ///
+    /// This finishes the output, so if it is compressed nothing else can be written to it
+    pub fn write<T: Serialize + ?Sized>(&self, value: &T) -> Result<(), IoError> {
+        let mut text = self.format.serialize(value, self.pretty).map_err(|err| {
+            IoError::new(
+                Operation::Serialize,
+                self.path(),
+                std::io::Error::new(std::io::ErrorKind::InvalidData, err),
+            )
+        })?;
+
+        if self.trailing_newline && !text.ends_with('\n') {
+            text.push('\n');
+        }
+
+        self.output.write_all(text.as_bytes())?;
+        self.output.finish()
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// The path of the file, or `<stdout>` if this output writes to stdout
+    pub fn path(&self) -> &Path {
+        self.output.path()
+    }
+
+    pub fn output(&self) -> &NamedOutputFile {
+        &self.output
+    }
+}
+
+impl clap::builder::TypedValueParser for TypedOutputFileParser {
+    type Value = TypedOutputFile;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        self.parse(cmd, arg, value.into())
+    }
+
+    fn parse(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: std::ffi::OsString,
+    ) -> Result<Self::Value, clap::Error> {
+        let format = match self.format {
+            Some(format) => format,
+            None if value == "-" => Format::ENABLED[0],
+            None => Format::from_path(Path::new(&value)).ok_or_else(|| {
+                let extensions = Format::ENABLED
+                    .iter()
+                    .map(|format| format.extension())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                crate::value_error(
+                    cmd,
+                    arg,
+                    &value,
+                    format!("could not infer the output format, expected one of the extensions: {extensions}"),
+                )
+            })?,
+        };
+
+        let output = self.output.parse(cmd, arg, value)?;
+
+        Ok(TypedOutputFile {
+            output,
+            format,
+            pretty: self.pretty,
+            trailing_newline: self.trailing_newline,
+        })
+    }
+}